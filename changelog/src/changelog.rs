@@ -1,7 +1,7 @@
 use std::collections::{HashMap, HashSet};
 use std::ops::{Add, AddAssign};
 
-use crate::{BreakingInfo, Change, ChangeType, SemverScope};
+use crate::{BreakingInfo, Change, ChangeFilter, ChangeType, Contributor, ScopeNames, SemverScope};
 
 #[derive(Debug, Clone, Eq, PartialEq, Default)]
 pub struct ChangeLog {
@@ -9,42 +9,46 @@ pub struct ChangeLog {
     features: Section,
     fixes: Section,
     scopes: HashSet<Option<Scope>>,
+    scope_names: ScopeNames,
+    filter: ChangeFilter,
+    bodies: Bodies,
+    migration_guide: bool,
+    migration_notes: Vec<MigrationNote>,
+    show_contributors: bool,
+    contributors: Vec<Contributor>,
 }
 
 pub(crate) type Scope = String;
-pub(crate) type Section = HashMap<Option<Scope>, Vec<String>>;
+pub(crate) type Section = HashMap<Option<Scope>, Vec<Entry>>;
 
-impl AddAssign<Change<'_>> for ChangeLog {
-    fn add_assign(&mut self, change: Change<'_>) {
-        let scope = change.scope.map(String::from);
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct Entry {
+    pub(crate) description: String,
+    pub(crate) body: Option<String>,
+}
 
-        match change.breaking {
-            BreakingInfo::NotBreaking => (),
-            BreakingInfo::Breaking => Self::append(
-                &mut self.scopes,
-                &mut self.breaking_changes,
-                scope.clone(),
-                change.description,
-            ),
-            BreakingInfo::BreakingWithDescriptions(descriptions) => {
-                for desc in descriptions {
-                    Self::append(
-                        &mut self.scopes,
-                        &mut self.breaking_changes,
-                        scope.clone(),
-                        desc,
-                    );
-                }
-            }
-        }
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct MigrationNote {
+    pub(crate) scope: Option<Scope>,
+    pub(crate) description: String,
+    pub(crate) text: String,
+    pub(crate) commit: Option<String>,
+}
 
-        let section = match change.type_ {
-            ChangeType::Fix => &mut self.fixes,
-            ChangeType::Feature => &mut self.features,
-            ChangeType::Custom(_) => return,
-        };
+/// Sections of the changelog in which the commit bodies are rendered under their entry
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum Bodies {
+    #[default]
+    Hidden,
+    All,
+    BreakingChangesOnly,
+    FeaturesOnly,
+}
 
-        Self::append(&mut self.scopes, section, scope, change.description);
+impl AddAssign<Change<'_>> for ChangeLog {
+    #[inline]
+    fn add_assign(&mut self, change: Change<'_>) {
+        self.add(change, None);
     }
 }
 
@@ -59,6 +63,68 @@ impl Add<Change<'_>> for ChangeLog {
 }
 
 impl ChangeLog {
+    /// Groups and names the scopes of the changes added from now on according to `scope_names`
+    pub fn with_scope_names(mut self, scope_names: ScopeNames) -> Self {
+        self.scope_names = scope_names;
+        self
+    }
+
+    /// Leaves the changes added from now on out of the changelog if they are excluded by `filter`
+    pub fn with_filter(mut self, filter: ChangeFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Renders the commit bodies under their entry in the given sections
+    pub fn with_bodies(mut self, bodies: Bodies) -> Self {
+        self.bodies = bodies;
+        self
+    }
+
+    /// Renders the full text of the `BREAKING CHANGE` footers in a dedicated "Migration guide" section
+    ///
+    /// The breaking changes section then lists the descriptions of the breaking commits instead of their footers.
+    pub fn with_migration_guide(mut self, enabled: bool) -> Self {
+        self.migration_guide = enabled;
+        self
+    }
+
+    /// Renders a "Contributors" section crediting the authors and co-authors of the changelog entries
+    pub fn with_contributors(mut self, enabled: bool) -> Self {
+        self.show_contributors = enabled;
+        self
+    }
+
+    /// Credits the contributor, unless it is excluded by the filter or already credited (with the same email)
+    pub fn add_contributor(&mut self, contributor: Contributor) {
+        if self.filter.excludes_contributor(&contributor)
+            || self
+                .contributors
+                .iter()
+                .any(|it| it.email.eq_ignore_ascii_case(&contributor.email))
+        {
+            return;
+        }
+        self.contributors.push(contributor);
+    }
+
+    /// Contributors in order of first contribution
+    pub fn contributors(&self) -> &[Contributor] {
+        &self.contributors
+    }
+
+    pub fn contributors_mut(&mut self) -> &mut [Contributor] {
+        &mut self.contributors
+    }
+
+    /// Adds a change parsed from the commit identified by `commit_id`
+    ///
+    /// Returns whether the change is an entry of the changelog (it isn't excluded, and it is breaking or a feature or fix).
+    #[inline]
+    pub fn add_commit(&mut self, commit_id: &str, change: Change<'_>) -> bool {
+        self.add(change, Some(commit_id))
+    }
+
     pub fn has_feature_or_fix(&self) -> bool {
         !self.features.is_empty() || !self.fixes.is_empty()
     }
@@ -79,6 +145,22 @@ impl ChangeLog {
         self.scopes.iter()
     }
 
+    pub(crate) fn scope_names(&self) -> &ScopeNames {
+        &self.scope_names
+    }
+
+    pub(crate) fn bodies(&self) -> Bodies {
+        self.bodies
+    }
+
+    pub(crate) fn show_contributors(&self) -> bool {
+        self.show_contributors
+    }
+
+    pub(crate) fn migration_notes(&self) -> &[MigrationNote] {
+        &self.migration_notes
+    }
+
     pub(crate) fn breaking_changes(&self) -> &Section {
         &self.breaking_changes
     }
@@ -91,16 +173,89 @@ impl ChangeLog {
         &self.fixes
     }
 
+    fn add(&mut self, change: Change<'_>, commit: Option<&str>) -> bool {
+        let scope = change.scope.map(|it| self.scope_names.resolve(it));
+
+        if self.filter.excludes(&change, scope.as_deref()) {
+            return false;
+        }
+
+        let breaking = change.breaking != BreakingInfo::NotBreaking;
+        match change.breaking {
+            BreakingInfo::NotBreaking => (),
+            BreakingInfo::Breaking => Self::append(
+                &mut self.scopes,
+                &mut self.breaking_changes,
+                scope.clone(),
+                change.description,
+                change.body,
+            ),
+            BreakingInfo::BreakingWithDescriptions(descriptions) if self.migration_guide => {
+                Self::append(
+                    &mut self.scopes,
+                    &mut self.breaking_changes,
+                    scope.clone(),
+                    change.description,
+                    change.body,
+                );
+                for text in descriptions {
+                    if !self.migration_notes.iter().any(|it| it.text == text) {
+                        self.migration_notes.push(MigrationNote {
+                            scope: scope.clone(),
+                            description: change.description.to_owned(),
+                            text: text.to_owned(),
+                            commit: commit.map(String::from),
+                        });
+                    }
+                }
+            }
+            BreakingInfo::BreakingWithDescriptions(descriptions) => {
+                // The body of the commit is rendered once, under its first breaking change
+                for (index, desc) in descriptions.into_iter().enumerate() {
+                    Self::append(
+                        &mut self.scopes,
+                        &mut self.breaking_changes,
+                        scope.clone(),
+                        desc,
+                        change.body.filter(|_| index == 0),
+                    );
+                }
+            }
+        }
+
+        let section = match change.type_ {
+            ChangeType::Fix => &mut self.fixes,
+            ChangeType::Feature => &mut self.features,
+            ChangeType::Custom(_) => return breaking,
+        };
+
+        Self::append(
+            &mut self.scopes,
+            section,
+            scope,
+            change.description,
+            change.body,
+        );
+        true
+    }
+
     fn append(
         scopes: &mut HashSet<Option<Scope>>,
         section: &mut Section,
         scope: Option<Scope>,
         value: &str,
+        body: Option<&str>,
     ) {
         if !scopes.contains(&scope) {
             scopes.insert(scope.clone());
         }
-        section.entry(scope).or_default().push(value.to_owned());
+        let entries = section.entry(scope).or_default();
+        if !entries.iter().any(|it| it.description == value) {
+            entries.push(Entry {
+                description: value.to_owned(),
+                body: body.map(String::from),
+            });
+        }
     }
 }
 
@@ -110,6 +265,15 @@ mod tests {
 
     use super::*;
 
+    fn descriptions<'a>(section: &'a Section, scope: &Option<Scope>) -> Vec<&'a str> {
+        section
+            .get(scope)
+            .expect("Entry not added")
+            .iter()
+            .map(|entry| entry.description.as_str())
+            .collect()
+    }
+
     #[test]
     pub fn is_empty_by_default() {
         let default = ChangeLog::default();
@@ -152,11 +316,8 @@ mod tests {
         );
         assert_eq!(changelog.semver_scope(), Some(SemverScope::Feature));
         assert_eq!(
-            changelog
-                .features()
-                .get(&scope.map(String::from))
-                .expect("Entry not added"),
-            &vec![change1.description, change2.description],
+            descriptions(changelog.features(), &scope.map(String::from)),
+            vec![change1.description, change2.description],
         );
     }
 
@@ -179,11 +340,8 @@ mod tests {
         );
         assert_eq!(changelog.semver_scope(), Some(SemverScope::Fix));
         assert_eq!(
-            changelog
-                .fixes()
-                .get(&scope.map(String::from))
-                .expect("Entry not added"),
-            &vec![change1.description, change2.description],
+            descriptions(changelog.fixes(), &scope.map(String::from)),
+            vec![change1.description, change2.description],
         );
     }
 
@@ -200,11 +358,8 @@ mod tests {
 
         assert_eq!(changelog.semver_scope(), Some(SemverScope::Breaking));
         assert_eq!(
-            changelog
-                .breaking_changes
-                .get(&None)
-                .expect("Entry not added"),
-            &vec![String::from(description)],
+            descriptions(changelog.breaking_changes(), &None),
+            vec![description],
         );
     }
 
@@ -220,12 +375,152 @@ mod tests {
         let changelog = ChangeLog::default() + change;
 
         assert_eq!(changelog.semver_scope(), Some(SemverScope::Breaking));
+        assert_eq!(
+            descriptions(changelog.breaking_changes(), &None),
+            vec!["one", "two"],
+        );
+    }
+
+    #[test]
+    fn groups_scope_aliases_together() {
+        let mut names = ScopeNames::default();
+        names.add_alias("cli", "CLI");
+        names.add_alias("command-line", "CLI");
+
+        let changelog = ChangeLog::default().with_scope_names(names)
+            + Change {
+                scope: Some("cli"),
+                ..Change::new(ChangeType::Feature, "one")
+            }
+            + Change {
+                scope: Some("command-line"),
+                ..Change::new(ChangeType::Feature, "two")
+            }
+            + Change {
+                scope: Some("CLI"),
+                ..Change::new(ChangeType::Feature, "three")
+            };
+
+        assert_eq!(
+            changelog.scopes().cloned().collect::<Vec<_>>(),
+            vec![Some(String::from("CLI"))]
+        );
+        assert_eq!(
+            descriptions(changelog.features(), &Some(String::from("CLI"))),
+            vec!["one", "two", "three"],
+        );
+    }
+
+    #[test]
+    fn ignores_duplicated_descriptions() {
+        let changelog = ChangeLog::default()
+            + Change::new(ChangeType::Feature, "Hello world!")
+            + Change::new(ChangeType::Feature, "Hello world!");
+
+        assert_eq!(
+            descriptions(changelog.features(), &None),
+            vec!["Hello world!"],
+        );
+    }
+
+    #[test]
+    fn stores_bodies() {
+        let changelog = ChangeLog::default()
+            + Change {
+                body: Some("Some details"),
+                breaking: BreakingInfo::BreakingWithDescriptions(vec!["oops"]),
+                ..Change::new(ChangeType::Feature, "Hello world!")
+            };
+
+        assert_eq!(
+            changelog.features().get(&None).expect("Entry not added")[0].body,
+            Some(String::from("Some details"))
+        );
         assert_eq!(
             changelog
-                .breaking_changes
+                .breaking_changes()
                 .get(&None)
-                .expect("Entry not added"),
-            &vec![String::from("one"), String::from("two")],
+                .expect("Entry not added")[0]
+                .body,
+            Some(String::from("Some details"))
+        );
+    }
+
+    #[test]
+    fn collects_migration_notes() {
+        let mut changelog = ChangeLog::default().with_migration_guide(true);
+        changelog.add_commit(
+            "0123456789abcdef",
+            Change {
+                scope: Some("cli"),
+                breaking: BreakingInfo::BreakingWithDescriptions(vec!["Use this\ninstead"]),
+                ..Change::new(ChangeType::Feature, "Hello world!")
+            },
+        );
+
+        assert_eq!(
+            descriptions(changelog.breaking_changes(), &Some(String::from("cli"))),
+            vec!["Hello world!"]
+        );
+        assert_eq!(
+            changelog.migration_notes(),
+            &[MigrationNote {
+                scope: Some(String::from("cli")),
+                description: String::from("Hello world!"),
+                text: String::from("Use this\ninstead"),
+                commit: Some(String::from("0123456789abcdef")),
+            }]
+        );
+    }
+
+    #[test]
+    fn no_migration_notes_by_default() {
+        let changelog = ChangeLog::default()
+            + Change {
+                breaking: BreakingInfo::BreakingWithDescriptions(vec!["oops"]),
+                ..Change::new(ChangeType::Feature, "Hello world!")
+            };
+
+        assert!(changelog.migration_notes().is_empty());
+        assert_eq!(
+            descriptions(changelog.breaking_changes(), &None),
+            vec!["oops"]
         );
     }
+
+    #[test]
+    fn credits_contributors_once() {
+        let mut filter = ChangeFilter::default();
+        filter.exclude_contributor("bot").expect("Invalid regex");
+        let mut changelog = ChangeLog::default().with_filter(filter);
+
+        changelog.add_contributor(Contributor::new("Jane", "jane@example.com").with_commit("abc"));
+        changelog.add_contributor(Contributor::new("Jane D.", "Jane@example.com"));
+        changelog.add_contributor(Contributor::new("Some bot", "bot@example.com"));
+        changelog.add_contributor(Contributor::new("John", "john@example.com"));
+
+        assert_eq!(
+            changelog.contributors(),
+            &[
+                Contributor::new("Jane", "jane@example.com").with_commit("abc"),
+                Contributor::new("John", "john@example.com"),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_excluded_changes() {
+        let mut filter = ChangeFilter::default();
+        filter.exclude_scope("internal");
+
+        let changelog = ChangeLog::default().with_filter(filter)
+            + Change {
+                scope: Some("internal"),
+                breaking: BreakingInfo::Breaking,
+                ..Change::new(ChangeType::Fix, "Hello world!")
+            };
+
+        assert!(changelog.semver_scope().is_none());
+        assert!(changelog.scopes().next().is_none());
+    }
 }
@@ -0,0 +1,79 @@
+/// Author (or co-author) of a commit included in a [`ChangeLog`](crate::ChangeLog)
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Contributor {
+    pub name: String,
+    pub email: String,
+
+    /// Commit authored by the contributor, if any (co-authors don't have one)
+    pub commit: Option<String>,
+
+    /// Github handle (without `@`)
+    pub handle: Option<String>,
+}
+
+impl Contributor {
+    /// Creates a contributor, deducing the github handle from the email if it is a github no-reply address
+    pub fn new(name: impl Into<String>, email: impl Into<String>) -> Self {
+        let email = email.into();
+        Self {
+            name: name.into(),
+            handle: noreply_handle(&email).map(String::from),
+            email,
+            commit: None,
+        }
+    }
+
+    #[inline]
+    pub fn with_commit(mut self, commit: impl Into<String>) -> Self {
+        self.commit = Some(commit.into());
+        self
+    }
+}
+
+/// Returns the name and email of the `Co-authored-by` trailers of a commit message
+pub(crate) fn co_authors(message: &str) -> impl Iterator<Item = (&str, &str)> {
+    message.lines().filter_map(|line| {
+        let (token, value) = line.split_once(':')?;
+        if !token.trim().eq_ignore_ascii_case("Co-authored-by") {
+            return None;
+        }
+        let (name, email) = value.trim().strip_suffix('>')?.split_once('<')?;
+        Some((name.trim(), email.trim()))
+    })
+}
+
+fn noreply_handle(email: &str) -> Option<&str> {
+    let user = email.strip_suffix("@users.noreply.github.com")?;
+    let handle = user.split_once('+').map_or(user, |(_, handle)| handle);
+    Some(handle).filter(|it| !it.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case("jane@example.com", None)]
+    #[case("jane@users.noreply.github.com", Some("jane"))]
+    #[case("123456+jane@users.noreply.github.com", Some("jane"))]
+    fn deduce_handle_from_noreply_email(#[case] email: &str, #[case] expected: Option<&str>) {
+        assert_eq!(Contributor::new("Jane", email).handle.as_deref(), expected)
+    }
+
+    #[rstest]
+    #[case("feat: Hello", vec![])]
+    #[case(
+        "feat: Hello\n\nCo-authored-by: Jane Doe <jane@example.com>",
+        vec![("Jane Doe", "jane@example.com")]
+    )]
+    #[case(
+        "feat: Hello\n\nco-authored-by: Jane <jane@example.com>\nCo-Authored-By: John <john@example.com>\n",
+        vec![("Jane", "jane@example.com"), ("John", "john@example.com")]
+    )]
+    #[case("feat: Hello\n\nCo-authored-by: Jane", vec![])]
+    fn find_co_authors(#[case] message: &str, #[case] expected: Vec<(&str, &str)>) {
+        assert_eq!(co_authors(message).collect::<Vec<_>>(), expected)
+    }
+}
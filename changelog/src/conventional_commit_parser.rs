@@ -1,3 +1,6 @@
+use std::fmt::{self, Display, Formatter};
+
+use pest::error::{ErrorVariant, InputLocation, LineColLocation};
 use pest::Parser;
 
 use super::*;
@@ -6,10 +9,181 @@ use super::*;
 #[grammar = "conventional_commit.pest"]
 struct ConventionalCommitParser;
 
-pub(crate) fn parse(commit_msg: &str) -> Option<Change> {
+const EXPECTED_SUBJECT: &str = "<type>[(<scope>)][!]: <description>";
+
+/// Reason why a commit message isn't a conventional commit
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub expected: Vec<&'static str>,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ParseErrorKind {
+    EmptyMessage,
+    MissingType,
+    InvalidScope,
+    MissingDescription,
+    InvalidSubject,
+    MissingBlankLine,
+    InvalidMessage,
+}
+
+impl ParseErrorKind {
+    pub fn hint(self) -> String {
+        match self {
+            ParseErrorKind::EmptyMessage => String::from("The commit message is empty"),
+            ParseErrorKind::MissingType => format!(
+                "The subject doesn't start with a type (format: `{}`)",
+                EXPECTED_SUBJECT
+            ),
+            ParseErrorKind::InvalidScope => format!(
+                "The scope is not correctly enclosed in parentheses (format: `{}`)",
+                EXPECTED_SUBJECT
+            ),
+            ParseErrorKind::MissingDescription => format!(
+                "The subject has no description (format: `{}`)",
+                EXPECTED_SUBJECT
+            ),
+            ParseErrorKind::InvalidSubject => format!(
+                "The subject is not a conventional commit subject (format: `{}`)",
+                EXPECTED_SUBJECT
+            ),
+            ParseErrorKind::MissingBlankLine => {
+                String::from("The subject must be followed by a blank line")
+            }
+            ParseErrorKind::InvalidMessage => {
+                String::from("The commit message is not a conventional commit")
+            }
+        }
+    }
+}
+
+impl ParseError {
+    #[inline]
+    pub fn hint(&self) -> String {
+        self.kind.hint()
+    }
+
+    fn new(commit_msg: &str, error: pest::error::Error<Rule>) -> Self {
+        let (line, column) = match error.line_col {
+            LineColLocation::Pos(pos) | LineColLocation::Span(pos, _) => pos,
+        };
+        let offset = match error.location {
+            InputLocation::Pos(offset) | InputLocation::Span((offset, _)) => offset,
+        };
+
+        let positives = match error.variant {
+            ErrorVariant::ParsingError { positives, .. } => positives,
+            ErrorVariant::CustomError { .. } => Vec::new(),
+        };
+        let mut expected: Vec<&'static str> =
+            positives.iter().copied().filter_map(describe).collect();
+        expected.dedup();
+
+        Self {
+            line,
+            column,
+            expected,
+            kind: kind_of(commit_msg, line, offset, &positives),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line,
+            self.column,
+            self.hint()
+        )?;
+        if !self.expected.is_empty() {
+            write!(f, ", expected {}", self.expected.join(" or "))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn describe(rule: Rule) -> Option<&'static str> {
+    match rule {
+        Rule::feat | Rule::fix | Rule::custom_type => Some("a type"),
+        Rule::scope => Some("a scope"),
+        Rule::breaking_flag => Some("`!`"),
+        Rule::subject_separator => Some("`: `"),
+        Rule::description => Some("a description"),
+        Rule::footer => Some("a footer"),
+        Rule::EOI => Some("a blank line"),
+        _ => None,
+    }
+}
+
+/// Derives the reason of the failure from the rules that the grammar expected at the error position
+fn kind_of(commit_msg: &str, line: usize, offset: usize, positives: &[Rule]) -> ParseErrorKind {
+    let subject_is_blank = commit_msg
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .is_empty();
+    let found = commit_msg[offset..].chars().next();
+    let previous = commit_msg[..offset].chars().next_back();
+    let expects = |rule: Rule| positives.contains(&rule);
+
+    if line > 1 {
+        return if line == 2 && expects(Rule::EOI) {
+            ParseErrorKind::MissingBlankLine
+        } else {
+            ParseErrorKind::InvalidMessage
+        };
+    }
+
+    if expects(Rule::conventional_commit)
+        || expects(Rule::feat)
+        || expects(Rule::fix)
+        || expects(Rule::custom_type)
+    {
+        return if subject_is_blank {
+            ParseErrorKind::EmptyMessage
+        } else {
+            ParseErrorKind::MissingType
+        };
+    }
+
+    if expects(Rule::scope) || matches!(found, Some('(') | Some(')')) || previous == Some(')') {
+        return ParseErrorKind::InvalidScope;
+    }
+
+    if expects(Rule::subject_separator) {
+        // The whole subject was read as a type, because there is no separator at all
+        return match found {
+            None | Some('\n') | Some('\r') => ParseErrorKind::MissingType,
+            _ => ParseErrorKind::InvalidSubject,
+        };
+    }
+
+    if positives.is_empty() || expects(Rule::description) {
+        return ParseErrorKind::MissingDescription;
+    }
+
+    ParseErrorKind::InvalidSubject
+}
+
+pub(crate) fn parse(commit_msg: &str) -> Result<Change<'_>, ParseError> {
     let commit = ConventionalCommitParser::parse(Rule::conventional_commit, commit_msg)
-        .ok()?
-        .next()?;
+        .map_err(|err| ParseError::new(commit_msg, err))?
+        .next()
+        .ok_or_else(|| ParseError {
+            line: 1,
+            column: 1,
+            expected: Vec::new(),
+            kind: ParseErrorKind::InvalidMessage,
+        })?;
 
     let mut result = Change::new(ChangeType::Fix, "");
 
@@ -24,14 +198,24 @@ pub(crate) fn parse(commit_msg: &str) -> Option<Change> {
             Rule::body => result.body = Some(commit_part.as_str()),
             Rule::footer => {
                 let mut is_breaking = false;
+                let mut footer_token: &str = "";
                 let mut footer_content: &str = "";
                 for footer_part in commit_part.into_inner() {
                     match footer_part.as_rule() {
-                        Rule::breaking_change_token => is_breaking = true,
+                        Rule::footer_token => {
+                            footer_token = footer_part.as_str();
+                            is_breaking = footer_part
+                                .into_inner()
+                                .any(|it| it.as_rule() == Rule::breaking_change_token);
+                        }
                         Rule::footer_value => footer_content = footer_part.as_str(),
                         _ => (),
                     }
                 }
+                result.footers.push(Footer {
+                    token: footer_token,
+                    value: footer_content,
+                });
                 if is_breaking {
                     match &mut result.breaking {
                         BreakingInfo::NotBreaking | BreakingInfo::Breaking => {
@@ -48,5 +232,60 @@ pub(crate) fn parse(commit_msg: &str) -> Option<Change> {
         }
     }
 
-    Some(result)
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case("", ParseErrorKind::EmptyMessage)]
+    #[case("   \n\nHello", ParseErrorKind::EmptyMessage)]
+    #[case("Hello world", ParseErrorKind::MissingType)]
+    #[case(": Hello world", ParseErrorKind::MissingType)]
+    #[case("(scope): Hello world", ParseErrorKind::MissingType)]
+    #[case("feat(scope: Hello world", ParseErrorKind::InvalidScope)]
+    #[case("feat)scope(: Hello world", ParseErrorKind::InvalidScope)]
+    #[case("feat(): Hello world", ParseErrorKind::InvalidScope)]
+    #[case("feat(scope)x: Hello world", ParseErrorKind::InvalidScope)]
+    #[case("feat: ", ParseErrorKind::MissingDescription)]
+    #[case("feat:Hello world", ParseErrorKind::InvalidSubject)]
+    #[case("feat!!: Hello world", ParseErrorKind::InvalidSubject)]
+    #[case("feat: Hello\nworld", ParseErrorKind::MissingBlankLine)]
+    #[case("feat: Hello\nworld\n\nBody", ParseErrorKind::MissingBlankLine)]
+    #[case("fix(a)(b): Hello world", ParseErrorKind::InvalidScope)]
+    #[case("fix: \n\nBody", ParseErrorKind::MissingDescription)]
+    #[case("Merge branch 'main'", ParseErrorKind::MissingType)]
+    fn diagnoses_errors(#[case] message: &str, #[case] expected: ParseErrorKind) {
+        assert_eq!(parse(message).map_err(|err| err.kind), Err(expected))
+    }
+
+    #[rstest]
+    #[case("feat:Hello world", 1, 5, vec!["`!`", "`: `"])]
+    #[case("feat(): Hello world", 1, 6, vec!["a scope"])]
+    #[case("feat: Hello\nworld", 2, 1, vec!["a blank line"])]
+    fn locates_errors(
+        #[case] message: &str,
+        #[case] line: usize,
+        #[case] column: usize,
+        #[case] expected: Vec<&str>,
+    ) {
+        let error = parse(message).expect_err("Parsing should have failed");
+
+        assert_eq!((error.line, error.column), (line, column));
+        assert_eq!(error.expected, expected);
+    }
+
+    #[test]
+    fn displays_hint_and_location() {
+        let error = parse("feat(): Hello world").expect_err("Parsing should have failed");
+
+        assert_eq!(
+            error.to_string(),
+            "line 1, column 6: The scope is not correctly enclosed in parentheses (format: `<type>[(<scope>)][!]: <description>`), expected a scope"
+        );
+    }
 }
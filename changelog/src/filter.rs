@@ -0,0 +1,166 @@
+use std::collections::HashSet;
+
+use regex::Regex;
+
+use crate::{Change, Contributor};
+
+/// Criteria of the changes to leave out of a [`ChangeLog`](crate::ChangeLog)
+///
+/// Excluded changes are neither part of the release notes nor of the version computation.
+/// Changes having a `Changelog: skip` footer are always excluded.
+///
+/// The filter also defines the contributors (typically bots) that are not credited in the changelog.
+#[derive(Debug, Clone, Default)]
+pub struct ChangeFilter {
+    scopes: HashSet<String>,
+    descriptions: Vec<Regex>,
+    contributors: Vec<Regex>,
+}
+
+impl ChangeFilter {
+    pub fn exclude_scope(&mut self, scope: impl Into<String>) {
+        self.scopes.insert(scope.into());
+    }
+
+    pub fn exclude_description(&mut self, pattern: &str) -> Result<(), regex::Error> {
+        self.descriptions.push(Regex::new(pattern)?);
+        Ok(())
+    }
+
+    /// Excludes the contributors for which `pattern` matches `Name <email>`
+    pub fn exclude_contributor(&mut self, pattern: &str) -> Result<(), regex::Error> {
+        self.contributors.push(Regex::new(pattern)?);
+        Ok(())
+    }
+
+    pub(crate) fn excludes_contributor(&self, contributor: &Contributor) -> bool {
+        let identity = format!("{} <{}>", contributor.name, contributor.email);
+        self.contributors
+            .iter()
+            .any(|regex| regex.is_match(&identity))
+    }
+
+    /// Returns true if the change should be excluded. `resolved_scope` is the scope after alias resolution.
+    pub(crate) fn excludes(&self, change: &Change<'_>, resolved_scope: Option<&str>) -> bool {
+        change
+            .scope
+            .iter()
+            .chain(resolved_scope.iter())
+            .any(|scope| self.scopes.contains(*scope))
+            || self
+                .descriptions
+                .iter()
+                .any(|regex| regex.is_match(change.description))
+            || change
+                .footer_values("Changelog")
+                .any(|value| value.eq_ignore_ascii_case("skip"))
+    }
+}
+
+impl PartialEq for ChangeFilter {
+    fn eq(&self, other: &Self) -> bool {
+        self.scopes == other.scopes
+            && self
+                .descriptions
+                .iter()
+                .map(Regex::as_str)
+                .eq(other.descriptions.iter().map(Regex::as_str))
+            && self
+                .contributors
+                .iter()
+                .map(Regex::as_str)
+                .eq(other.contributors.iter().map(Regex::as_str))
+    }
+}
+
+impl Eq for ChangeFilter {}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::{ChangeType, Footer};
+
+    use super::*;
+
+    #[test]
+    fn excludes_nothing_by_default() {
+        let change = Change {
+            scope: Some("ci"),
+            ..Change::new(ChangeType::Fix, "Hello world")
+        };
+
+        assert!(!ChangeFilter::default().excludes(&change, change.scope));
+    }
+
+    #[rstest]
+    #[case(Some("internal"), None, true)]
+    #[case(Some("int"), Some("internal"), true)]
+    #[case(Some("cli"), Some("cli"), false)]
+    #[case(None, None, false)]
+    fn excludes_scopes(
+        #[case] scope: Option<&str>,
+        #[case] resolved_scope: Option<&str>,
+        #[case] expected: bool,
+    ) {
+        let mut filter = ChangeFilter::default();
+        filter.exclude_scope("internal");
+        let change = Change {
+            scope,
+            ..Change::new(ChangeType::Fix, "Hello world")
+        };
+
+        assert_eq!(filter.excludes(&change, resolved_scope), expected);
+    }
+
+    #[rstest]
+    #[case("bump dependencies", true)]
+    #[case("Bump dependencies", false)]
+    #[case("fix bump of dependencies", false)]
+    fn excludes_descriptions(#[case] description: &str, #[case] expected: bool) {
+        let mut filter = ChangeFilter::default();
+        filter.exclude_description("^bump ").expect("Invalid regex");
+        let change = Change::new(ChangeType::Fix, description);
+
+        assert_eq!(filter.excludes(&change, None), expected);
+    }
+
+    #[rstest]
+    #[case("skip", true)]
+    #[case("Skip", true)]
+    #[case("include", false)]
+    fn excludes_changes_with_skip_footer(#[case] value: &str, #[case] expected: bool) {
+        let change = Change {
+            footers: vec![Footer {
+                token: "Changelog",
+                value,
+            }],
+            ..Change::new(ChangeType::Feature, "Hello world")
+        };
+
+        assert_eq!(ChangeFilter::default().excludes(&change, None), expected);
+    }
+
+    #[rstest]
+    #[case(
+        "dependabot[bot]",
+        "49699333+dependabot[bot]@users.noreply.github.com",
+        true
+    )]
+    #[case("Renovate Bot", "bot@renovateapp.com", true)]
+    #[case("Jane Doe", "jane@example.com", false)]
+    fn excludes_contributors(#[case] name: &str, #[case] email: &str, #[case] expected: bool) {
+        let mut filter = ChangeFilter::default();
+        filter
+            .exclude_contributor(r"\[bot\]")
+            .expect("Invalid regex");
+        filter
+            .exclude_contributor("@renovateapp.com>$")
+            .expect("Invalid regex");
+
+        assert_eq!(
+            filter.excludes_contributor(&Contributor::new(name, email)),
+            expected
+        );
+    }
+}
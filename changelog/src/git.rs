@@ -1,36 +1,301 @@
-use git2::Sort;
+use git2::{Commit, Signature, Sort, Time};
 
-use super::{Change, ChangeLog};
+use super::{contributor, revert, ChangeLog, Contributor, ParseError, ParsingRules};
+
+/// Trailer added to the release commits, so that they are never considered as changes to release
+pub const RELEASE_TRAILER: &str = "Released-by: autorel";
 
 pub trait ChangeLogRepository {
     type Error: std::error::Error;
-    fn load_changelog(&self, from: Option<&str>) -> Result<ChangeLog, Self::Error>;
+
+    #[inline]
+    fn load_changelog(&self, from: Option<&str>) -> Result<ChangeLog, Self::Error> {
+        self.load_changelog_with(ChangeLog::default(), from, &LoadOptions::default())
+            .map(|it| it.changelog)
+    }
+
+    /// Adds the changes since `from` to the given `changelog`
+    #[inline]
+    fn load_changelog_with(
+        &self,
+        changelog: ChangeLog,
+        from: Option<&str>,
+        options: &LoadOptions,
+    ) -> Result<LoadedChangeLog, Self::Error> {
+        self.load_changelog_between(changelog, from, "HEAD", options)
+    }
+
+    /// Adds the changes reachable from `to` but not from `from` to the given `changelog`
+    ///
+    /// `from` and `to` can be any revision (tag, branch, commit sha, `HEAD~3`, etc.).
+    /// If `from` is `None`, all the history of `to` is loaded.
+    fn load_changelog_between(
+        &self,
+        changelog: ChangeLog,
+        from: Option<&str>,
+        to: &str,
+        options: &LoadOptions,
+    ) -> Result<LoadedChangeLog, Self::Error>;
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LoadedChangeLog {
+    pub changelog: ChangeLog,
+
+    /// Commits that were parsed, from the oldest to the newest
+    ///
+    /// The messages can be parsed again with the [`ParsingRules`] of the [`LoadOptions`] to get the changes.
+    pub commits: Vec<ParsedCommit>,
+
+    /// Commits that couldn't be parsed, and are therefore not part of the changelog
+    pub skipped: Vec<SkippedCommit>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParsedCommit {
+    pub id: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SkippedCommit {
+    pub id: String,
+    pub summary: String,
+    pub error: ParseError,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct LoadOptions {
+    pub strategy: HistoryStrategy,
+    pub rules: ParsingRules,
+}
+
+/// Which commits of the history are parsed to build the changelog
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum HistoryStrategy {
+    /// Every commit, including merge commits and the commits of the merged branches
+    #[default]
+    AllCommits,
+
+    /// Only the commits of the first-parent chain, ignoring the commits of the merged branches
+    FirstParent,
+
+    /// Only the commits of the first-parent chain, using the title of the merged pull-request for merge commits
+    MergeTitles,
 }
 
 impl ChangeLogRepository for git2::Repository {
     type Error = git2::Error;
 
-    fn load_changelog(&self, from: Option<&str>) -> Result<ChangeLog, Self::Error> {
+    fn load_changelog_between(
+        &self,
+        mut changelog: ChangeLog,
+        from: Option<&str>,
+        to: &str,
+        options: &LoadOptions,
+    ) -> Result<LoadedChangeLog, Self::Error> {
         let mut walker = self.revwalk()?;
-        walker.push_head()?;
+        walker.push(self.revparse_single(to)?.peel_to_commit()?.id())?;
         let _ = walker.set_sorting(Sort::REVERSE);
 
+        if options.strategy != HistoryStrategy::AllCommits {
+            walker.simplify_first_parent()?;
+        }
+
         if let Some(from) = from {
-            walker.push_range(&(String::from(from) + "..HEAD"))?;
+            walker.hide(self.revparse_single(from)?.peel_to_commit()?.id())?;
         }
 
-        let mut result = ChangeLog::default();
+        let commits = walker
+            .map(|oid| self.find_commit(oid?))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let messages: Vec<(String, String)> = commits
+            .iter()
+            .map(|commit| {
+                (
+                    commit.id().to_string(),
+                    message_of(commit, options.strategy),
+                )
+            })
+            .collect();
 
-        for oid in walker {
-            if let Some(change) = self
-                .find_commit(oid?)?
-                .message()
-                .and_then(Change::parse_conventional_commit)
-            {
-                result += change;
+        let reverted = revert::find_reverted(&messages);
+        let mailmap = self.mailmap()?;
+        let mut parsed = Vec::new();
+        let mut skipped = Vec::new();
+
+        for (index, (id, message)) in messages.iter().enumerate() {
+            if reverted.contains(&index) || is_release(message) {
+                continue;
+            }
+
+            match options.rules.try_parse(message) {
+                Ok(change) => {
+                    // Only the authors of the changelog entries are credited
+                    if changelog.add_commit(id, change) {
+                        add_contributors(
+                            &mut changelog,
+                            &commits[index],
+                            id,
+                            message,
+                            options.strategy,
+                            &mailmap,
+                        )?;
+                    }
+                    parsed.push(ParsedCommit {
+                        id: id.clone(),
+                        message: message.clone(),
+                    });
+                }
+                Err(error) => {
+                    let is_expected = revert::Revert::parse(message).is_some()
+                        || (commits[index].parent_count() > 1
+                            && options.strategy != HistoryStrategy::MergeTitles);
+
+                    if !is_expected {
+                        skipped.push(SkippedCommit {
+                            id: id.clone(),
+                            summary: message.lines().next().unwrap_or_default().to_owned(),
+                            error,
+                        });
+                    }
+                }
             }
         }
 
-        Ok(result)
+        Ok(LoadedChangeLog {
+            changelog,
+            commits: parsed,
+            skipped,
+        })
+    }
+}
+
+/// Credits the author (resolved with the mailmap) and the co-authors of the commit
+fn add_contributors(
+    changelog: &mut ChangeLog,
+    commit: &Commit<'_>,
+    id: &str,
+    message: &str,
+    strategy: HistoryStrategy,
+    mailmap: &git2::Mailmap,
+) -> Result<(), git2::Error> {
+    // With all commits, the authors of a merged branch are credited by their own commits
+    if commit.parent_count() <= 1 || strategy != HistoryStrategy::AllCommits {
+        let author = commit.author_with_mailmap(mailmap)?;
+        changelog.add_contributor(
+            Contributor::new(
+                author.name().unwrap_or_default(),
+                author.email().unwrap_or_default(),
+            )
+            .with_commit(id),
+        );
+    }
+    for (name, email) in contributor::co_authors(message) {
+        let co_author =
+            mailmap.resolve_signature(&Signature::new(name, email, &Time::new(0, 0))?)?;
+        changelog.add_contributor(Contributor::new(
+            co_author.name().unwrap_or_default(),
+            co_author.email().unwrap_or_default(),
+        ));
+    }
+    Ok(())
+}
+
+fn is_release(message: &str) -> bool {
+    message.lines().any(|line| line.trim() == RELEASE_TRAILER)
+}
+
+fn message_of(commit: &Commit<'_>, strategy: HistoryStrategy) -> String {
+    let message = commit.message().unwrap_or_default();
+
+    if strategy == HistoryStrategy::MergeTitles && commit.parent_count() > 1 {
+        if let Some(title) = merge_title(message) {
+            return title;
+        }
+    }
+
+    message.to_owned()
+}
+
+/// Extracts the title of the merged pull-request from a merge commit message
+///
+/// Merge commits created by github (or gitlab) have the pull-request title as first line after the subject.
+/// In case of github, the pull-request number is appended to the title.
+/// Other merge commits (e.g. `Merge branch 'x'`) have no pull-request title.
+fn merge_title(message: &str) -> Option<String> {
+    let (subject, body) = message.split_once("\n\n")?;
+    let title = body.lines().next()?.trim();
+
+    if title.is_empty() {
+        return None;
+    }
+
+    if let Some(number) = subject
+        .trim()
+        .strip_prefix("Merge pull request #")
+        .and_then(|it| it.split_whitespace().next())
+    {
+        return Some(format!("{} (#{})", title, number));
+    }
+
+    let is_gitlab_merge_request = subject.starts_with("Merge branch ")
+        && body
+            .lines()
+            .any(|line| line.starts_with("See merge request "));
+    if is_gitlab_merge_request {
+        return Some(title.to_owned());
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case("chore: release 1.2.3\n\nReleased-by: autorel", true)]
+    #[case("fix: release 1.2.3\n\nReleased-by: autorel\n", true)]
+    #[case("fix: release 1.2.3", false)]
+    #[case("fix: release 1.2.3\n\nReleased-by: someone", false)]
+    #[case("Release 1.2.3\n\nReleased-by: autorel", true)]
+    fn recognize_release_commits(#[case] message: &str, #[case] expected: bool) {
+        assert_eq!(is_release(message), expected)
+    }
+
+    #[rstest]
+    #[case("feat: Hello world")]
+    #[case("feat: Hello world\n\nMerge pull request #1 from user/branch")]
+    #[case("Merge branch 'main' into feature")]
+    #[case("Merge branch 'main' into feature\n\nfeat: Hello world")]
+    #[case("Merge branch 'feature'\n\n# Conflicts:\n#\tsrc/main.rs")]
+    #[case("Merge pull request #12 from user/branch\n\n")]
+    fn no_merge_title(#[case] message: &str) {
+        assert_eq!(merge_title(message), None)
+    }
+
+    #[rstest]
+    #[case(
+        "Merge pull request #12 from user/branch\n\nfeat: Hello world",
+        "feat: Hello world (#12)"
+    )]
+    #[case(
+        "Merge pull request #12 from user/branch\n\nfix: Hello world\n\nBREAKING CHANGE: oops",
+        "fix: Hello world (#12)"
+    )]
+    #[case(
+        "Merge pull request #12 from user/branch\n\nfeat: Hello world\nwith details\n\nAnd another paragraph",
+        "feat: Hello world (#12)"
+    )]
+    #[case(
+        "Merge branch 'feature' into 'main'\n\nfeat: Hello world\n\nSee merge request group/project!3",
+        "feat: Hello world"
+    )]
+    fn extract_merge_title(#[case] message: &str, #[case] expected: &str) {
+        assert_eq!(merge_title(message).as_deref(), Some(expected))
     }
 }
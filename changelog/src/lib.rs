@@ -2,12 +2,22 @@
 #[macro_use]
 extern crate pest_derive;
 
-pub use changelog::ChangeLog;
+pub use changelog::{Bodies, ChangeLog};
+pub use contributor::Contributor;
+pub use conventional_commit_parser::{ParseError, ParseErrorKind};
+pub use filter::ChangeFilter;
+pub use rules::ParsingRules;
+pub use scope::ScopeNames;
 
 mod changelog;
+mod contributor;
 mod conventional_commit_parser;
+mod filter;
 pub mod git;
 pub mod markdown;
+mod revert;
+mod rules;
+mod scope;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Change<'a> {
@@ -16,6 +26,13 @@ pub struct Change<'a> {
     pub breaking: BreakingInfo<'a>,
     pub description: &'a str,
     pub body: Option<&'a str>,
+    pub footers: Vec<Footer<'a>>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Footer<'a> {
+    pub token: &'a str,
+    pub value: &'a str,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -48,11 +65,25 @@ impl<'a> Change<'a> {
             breaking: BreakingInfo::NotBreaking,
             description,
             body: None,
+            footers: Vec::new(),
         }
     }
 
+    /// Returns the values of the footers having the given token (case-insensitive)
+    pub fn footer_values<'s>(&'s self, token: &'s str) -> impl Iterator<Item = &'a str> + 's {
+        self.footers
+            .iter()
+            .filter(move |footer| footer.token.eq_ignore_ascii_case(token))
+            .map(|footer| footer.value.trim())
+    }
+
     #[inline]
     pub fn parse_conventional_commit(message: &'a str) -> Option<Self> {
+        Self::try_parse_conventional_commit(message).ok()
+    }
+
+    #[inline]
+    pub fn try_parse_conventional_commit(message: &'a str) -> Result<Self, ParseError> {
         conventional_commit_parser::parse(message)
     }
 }
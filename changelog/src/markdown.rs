@@ -1,11 +1,11 @@
 use std::fmt;
 use std::fmt::{Display, Formatter};
 
-use crate::changelog::{Scope, Section};
-use crate::ChangeLog;
+use crate::changelog::{Bodies, MigrationNote, Scope, Section};
+use crate::{ChangeLog, Contributor, ScopeNames};
 
 impl ChangeLog {
-    pub fn markdown(&self) -> MarkdownChangelog {
+    pub fn markdown(&self) -> MarkdownChangelog<'_> {
         let mut scopes: Vec<&Option<Scope>> = self.scopes().collect();
         scopes.sort();
 
@@ -14,16 +14,33 @@ impl ChangeLog {
                 title: "Breaking changes",
                 scopes: scopes.clone(),
                 section: self.breaking_changes(),
+                scope_names: self.scope_names(),
+                show_bodies: matches!(self.bodies(), Bodies::All | Bodies::BreakingChangesOnly),
+            },
+            migration_guide: MarkdownMigrationGuide {
+                notes: self.migration_notes(),
+                scope_names: self.scope_names(),
             },
             features: MarkdownChangelogSection {
                 title: "Features",
                 scopes: scopes.clone(),
                 section: self.features(),
+                scope_names: self.scope_names(),
+                show_bodies: matches!(self.bodies(), Bodies::All | Bodies::FeaturesOnly),
             },
             fixes: MarkdownChangelogSection {
                 title: "Bug fixes",
                 scopes,
                 section: self.fixes(),
+                scope_names: self.scope_names(),
+                show_bodies: self.bodies() == Bodies::All,
+            },
+            contributors: MarkdownContributors {
+                contributors: if self.show_contributors() {
+                    self.contributors()
+                } else {
+                    &[]
+                },
             },
         }
     }
@@ -32,8 +49,10 @@ impl ChangeLog {
 #[derive(Debug, Clone)]
 pub struct MarkdownChangelog<'a> {
     breaking_changes: MarkdownChangelogSection<'a>,
+    migration_guide: MarkdownMigrationGuide<'a>,
     features: MarkdownChangelogSection<'a>,
     fixes: MarkdownChangelogSection<'a>,
+    contributors: MarkdownContributors<'a>,
 }
 
 #[derive(Debug, Clone)]
@@ -41,18 +60,77 @@ struct MarkdownChangelogSection<'a> {
     title: &'a str,
     scopes: Vec<&'a Option<Scope>>,
     section: &'a Section,
+    scope_names: &'a ScopeNames,
+    show_bodies: bool,
+}
+
+#[derive(Debug, Clone)]
+struct MarkdownMigrationGuide<'a> {
+    notes: &'a [MigrationNote],
+    scope_names: &'a ScopeNames,
+}
+
+#[derive(Debug, Clone)]
+struct MarkdownContributors<'a> {
+    contributors: &'a [Contributor],
 }
 
 impl Display for MarkdownChangelog<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "{}{}{}",
-            self.breaking_changes, self.features, self.fixes
+            "{}{}{}{}{}",
+            self.breaking_changes,
+            self.migration_guide,
+            self.features,
+            self.fixes,
+            self.contributors
         )
     }
 }
 
+impl Display for MarkdownContributors<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.contributors.is_empty() {
+            return Ok(());
+        }
+        write!(f, "### Contributors\n\n")?;
+
+        for contributor in self.contributors {
+            match &contributor.handle {
+                Some(handle) => writeln!(f, "* @{}", handle)?,
+                None => writeln!(f, "* {}", contributor.name)?,
+            }
+        }
+
+        write!(f, "\n\n")
+    }
+}
+
+impl Display for MarkdownMigrationGuide<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.notes.is_empty() {
+            return Ok(());
+        }
+        write!(f, "### Migration guide\n\n")?;
+
+        for note in self.notes {
+            write!(f, "#### ")?;
+            if let Some(scope) = &note.scope {
+                write!(f, "{}: ", self.scope_names.title(scope))?;
+            }
+            write!(f, "{}", note.description)?;
+            if let Some(commit) = &note.commit {
+                write!(f, " ({:.7})", commit)?;
+            }
+            write!(f, "\n\n")?;
+            writeln!(f, "{}\n", note.text.trim())?;
+        }
+
+        writeln!(f)
+    }
+}
+
 impl Display for MarkdownChangelogSection<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         if self.section.is_empty() {
@@ -66,12 +144,26 @@ impl Display for MarkdownChangelogSection<'_> {
             .flat_map(|scope| self.section.get(scope).map(|changes| (scope, changes)));
 
         for (scope, changes) in iter {
-            if let Some(title) = scope {
-                write!(f, "#### {}\n\n", title)?;
+            if let Some(scope) = scope {
+                write!(f, "#### {}\n\n", self.scope_names.title(scope))?;
             }
 
             for change in changes {
-                writeln!(f, "* {}", change)?;
+                writeln!(f, "* {}", change.description)?;
+                match &change.body {
+                    Some(body) if self.show_bodies => {
+                        writeln!(f)?;
+                        for line in body.trim().lines() {
+                            if line.trim().is_empty() {
+                                writeln!(f)?;
+                            } else {
+                                writeln!(f, "  {}", line.trim_end())?;
+                            }
+                        }
+                        writeln!(f)?;
+                    }
+                    _ => (),
+                }
             }
             writeln!(f)?;
         }
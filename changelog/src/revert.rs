@@ -0,0 +1,198 @@
+use std::collections::HashSet;
+
+use crate::{Change, ChangeType};
+
+/// Sentence of the body of the revert commits created by git, followed by the sha of the reverted commit
+const GIT_REVERT_SENTENCE: &str = "This reverts commit ";
+
+/// Reference to the commit cancelled by a revert commit
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) struct Revert<'a> {
+    header: &'a str,
+    commit: Option<&'a str>,
+}
+
+impl<'a> Revert<'a> {
+    /// Recognizes both the conventional `revert: <header>` commits
+    /// and the default git messages (`Revert "<header>"`)
+    pub(crate) fn parse(message: &'a str) -> Option<Self> {
+        let git_header = message
+            .lines()
+            .next()
+            .and_then(|subject| subject.trim().strip_prefix("Revert \""))
+            .and_then(|it| it.strip_suffix('"'));
+
+        let conventional = match git_header {
+            Some(_) => None,
+            None => Change::parse_conventional_commit(message).filter(is_revert),
+        };
+
+        let header = git_header.or_else(|| conventional.as_ref().map(|it| it.description))?;
+
+        let commit = message
+            .split_once(GIT_REVERT_SENTENCE)
+            .and_then(|(_, rest)| rest.split_whitespace().next())
+            .map(|it| it.trim_end_matches('.'))
+            .or_else(|| {
+                conventional
+                    .as_ref()
+                    .and_then(|it| it.footer_values("Refs").next())
+                    .map(str::trim)
+            })
+            .filter(|it| is_commit_id(it));
+
+        Some(Self { header, commit })
+    }
+
+    fn matches(&self, commit_id: &str, message: &str) -> bool {
+        match self.commit {
+            Some(commit) => commit_id.starts_with(commit),
+            None => message.lines().next().map(str::trim) == Some(self.header),
+        }
+    }
+}
+
+/// Returns the indices of the commits that are reverted by a more recent commit of the list
+///
+/// The commits must be given from the oldest to the most recent, as pairs of commit id and message.
+/// A revert commit that is itself reverted doesn't cancel anything.
+/// A revert without commit sha cancels only the most recent commit having the reverted header.
+pub(crate) fn find_reverted(commits: &[(String, String)]) -> HashSet<usize> {
+    let mut pending: Vec<Revert<'_>> = Vec::new();
+    let mut reverted = HashSet::new();
+
+    for (index, (id, message)) in commits.iter().enumerate().rev() {
+        if let Some(position) = pending.iter().position(|it| it.matches(id, message)) {
+            pending.remove(position);
+            reverted.insert(index);
+        } else if let Some(revert) = Revert::parse(message) {
+            pending.push(revert);
+        }
+    }
+
+    reverted
+}
+
+fn is_revert(change: &Change<'_>) -> bool {
+    match change.type_ {
+        ChangeType::Custom(type_) => type_.eq_ignore_ascii_case("revert"),
+        _ => false,
+    }
+}
+
+/// Whether the text is an (abbreviated) commit sha, rather than an issue reference like `#42`
+fn is_commit_id(text: &str) -> bool {
+    (7..=40).contains(&text.len()) && text.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case("feat: Hello world")]
+    #[case("Hello world")]
+    #[case("Reverting things")]
+    #[case("fix: This reverts commit 1234567")]
+    fn not_a_revert(#[case] message: &str) {
+        assert_eq!(Revert::parse(message), None)
+    }
+
+    #[rstest]
+    #[case("revert: feat: Hello world", "feat: Hello world", None)]
+    #[case("Revert: feat: Hello world", "feat: Hello world", None)]
+    #[case(
+        "revert: feat: Hello world\n\nRefs: 1234567",
+        "feat: Hello world",
+        Some("1234567")
+    )]
+    #[case("revert: feat: Hello world\n\nRefs: #42", "feat: Hello world", None)]
+    #[case(
+        "Revert \"feat: Hello world\"\n\nThis reverts commit 0123456789abcdef.",
+        "feat: Hello world",
+        Some("0123456789abcdef")
+    )]
+    #[case("Revert \"feat: Hello world\"", "feat: Hello world", None)]
+    #[case(
+        "Revert \"Revert \"feat: Hello world\"\"",
+        "Revert \"feat: Hello world\"",
+        None
+    )]
+    fn parse_revert(
+        #[case] message: &str,
+        #[case] expected_header: &str,
+        #[case] expected_commit: Option<&str>,
+    ) {
+        assert_eq!(
+            Revert::parse(message),
+            Some(Revert {
+                header: expected_header,
+                commit: expected_commit
+            })
+        )
+    }
+
+    fn commits(messages: &[&str]) -> Vec<(String, String)> {
+        messages
+            .iter()
+            .enumerate()
+            .map(|(index, message)| {
+                (
+                    format!("{:040x}", index + 0xabcdef0),
+                    String::from(*message),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finds_reverted_commit_by_header() {
+        let commits = commits(&["feat: one", "feat: two", "revert: feat: one"]);
+
+        assert_eq!(find_reverted(&commits), vec![0].into_iter().collect());
+    }
+
+    #[test]
+    fn finds_reverted_commit_by_id() {
+        let commits = commits(&[
+            "feat: same",
+            "feat: same",
+            "Revert \"feat: same\"\n\nThis reverts commit 000000000000000000000000000000000abcdef1.",
+        ]);
+
+        assert_eq!(find_reverted(&commits), vec![1].into_iter().collect());
+    }
+
+    #[test]
+    fn cancels_only_the_most_recent_commit_with_the_same_header() {
+        let commits = commits(&[
+            "feat: same",
+            "feat: other",
+            "feat: same",
+            "feat: another",
+            "revert: feat: same",
+        ]);
+
+        assert_eq!(find_reverted(&commits), vec![2].into_iter().collect());
+    }
+
+    #[test]
+    fn revert_of_a_revert_restores_the_change() {
+        let commits = commits(&[
+            "feat: one",
+            "Revert \"feat: one\"",
+            "Revert \"Revert \"feat: one\"\"",
+        ]);
+
+        assert_eq!(find_reverted(&commits), vec![1].into_iter().collect());
+    }
+
+    #[test]
+    fn ignores_revert_of_commit_out_of_range() {
+        let commits = commits(&["feat: one", "revert: feat: two"]);
+
+        assert!(find_reverted(&commits).is_empty());
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use regex::Regex;
+
+use crate::{BreakingInfo, Change, ChangeType, ParseError};
+
+/// Rules to recognize the changes from commit messages that don't strictly follow the conventional commit convention
+///
+/// Type aliases are resolved (case-insensitively) on conventional commits having a custom type.
+/// Fallbacks are tried in order on the subject of the commits that are not conventional commits.
+/// A fallback pattern may capture the `description` and `scope` named groups,
+/// otherwise the whole subject is used as description.
+#[derive(Debug, Clone, Default)]
+pub struct ParsingRules {
+    type_aliases: HashMap<String, ChangeType<'static>>,
+    fallbacks: Vec<Fallback>,
+}
+
+#[derive(Debug, Clone)]
+struct Fallback {
+    pattern: Regex,
+    type_: ChangeType<'static>,
+    breaking: bool,
+}
+
+impl ParsingRules {
+    pub fn add_type_alias(&mut self, alias: &str, type_: ChangeType<'static>) {
+        self.type_aliases.insert(alias.to_lowercase(), type_);
+    }
+
+    pub fn add_fallback(
+        &mut self,
+        pattern: &str,
+        type_: ChangeType<'static>,
+        breaking: bool,
+    ) -> Result<(), regex::Error> {
+        self.fallbacks.push(Fallback {
+            pattern: Regex::new(pattern)?,
+            type_,
+            breaking,
+        });
+        Ok(())
+    }
+
+    #[inline]
+    pub fn parse<'a>(&self, message: &'a str) -> Option<Change<'a>> {
+        self.try_parse(message).ok()
+    }
+
+    /// Returns the conventional commit parsing error if no fallback matches
+    pub fn try_parse<'a>(&self, message: &'a str) -> Result<Change<'a>, ParseError> {
+        match Change::try_parse_conventional_commit(message) {
+            Ok(mut change) => {
+                if let ChangeType::Custom(type_) = change.type_ {
+                    if let Some(alias) = self.type_aliases.get(&type_.to_lowercase()) {
+                        change.type_ = *alias;
+                    }
+                }
+                Ok(change)
+            }
+            Err(err) => self.parse_fallback(message).ok_or(err),
+        }
+    }
+
+    fn parse_fallback<'a>(&self, message: &'a str) -> Option<Change<'a>> {
+        let subject = message.lines().next()?.trim();
+
+        self.fallbacks.iter().find_map(|fallback| {
+            let captures = fallback.pattern.captures(subject)?;
+            let description = captures
+                .name("description")
+                .map_or(subject, |it| it.as_str().trim());
+
+            Some(Change {
+                scope: captures.name("scope").map(|it| it.as_str()),
+                breaking: if fallback.breaking {
+                    BreakingInfo::Breaking
+                } else {
+                    BreakingInfo::NotBreaking
+                },
+                ..Change::new(fallback.type_, description)
+            })
+        })
+    }
+}
+
+impl PartialEq for ParsingRules {
+    fn eq(&self, other: &Self) -> bool {
+        self.type_aliases == other.type_aliases
+            && self.fallbacks.len() == other.fallbacks.len()
+            && self.fallbacks.iter().zip(&other.fallbacks).all(|(a, b)| {
+                a.pattern.as_str() == b.pattern.as_str()
+                    && a.type_ == b.type_
+                    && a.breaking == b.breaking
+            })
+    }
+}
+
+impl Eq for ParsingRules {}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    fn gitmoji() -> ParsingRules {
+        let mut rules = ParsingRules::default();
+        rules
+            .add_fallback(
+                r"^:sparkles:\s*(?P<description>.+)",
+                ChangeType::Feature,
+                false,
+            )
+            .unwrap();
+        rules
+            .add_fallback(r"^:bug:\s*(?P<description>.+)", ChangeType::Fix, false)
+            .unwrap();
+        rules
+            .add_fallback(r"^:boom:", ChangeType::Feature, true)
+            .unwrap();
+        rules
+    }
+
+    #[rstest]
+    #[case("feature: Hello world", ChangeType::Feature)]
+    #[case("Feature(scope): Hello world", ChangeType::Feature)]
+    #[case("bugfix: Hello world", ChangeType::Fix)]
+    #[case("HOTFIX: Hello world", ChangeType::Fix)]
+    #[case("fix: Hello world", ChangeType::Fix)]
+    #[case("chore: Hello world", ChangeType::Custom("chore"))]
+    fn resolves_type_aliases(#[case] message: &str, #[case] expected: ChangeType<'_>) {
+        let mut rules = ParsingRules::default();
+        rules.add_type_alias("feature", ChangeType::Feature);
+        rules.add_type_alias("bugfix", ChangeType::Fix);
+        rules.add_type_alias("hotfix", ChangeType::Fix);
+
+        let change = rules.parse(message).expect("Failed to parse commit");
+
+        assert_eq!(change.type_, expected);
+    }
+
+    #[rstest]
+    #[case(
+        ":sparkles: Hello world",
+        Change::new(ChangeType::Feature, "Hello world")
+    )]
+    #[case(
+        ":bug: Hello world\n\nbody",
+        Change::new(ChangeType::Fix, "Hello world")
+    )]
+    #[case(":boom: Hello world", Change { breaking: BreakingInfo::Breaking, ..Change::new(ChangeType::Feature, ":boom: Hello world") })]
+    fn uses_fallbacks(#[case] message: &str, #[case] expected: Change<'_>) {
+        assert_eq!(gitmoji().parse(message), Some(expected));
+    }
+
+    #[test]
+    fn fallback_can_capture_scope() {
+        let mut rules = ParsingRules::default();
+        rules
+            .add_fallback(
+                r"^\[(?P<scope>\w+)\] (?P<description>.+)",
+                ChangeType::Fix,
+                false,
+            )
+            .unwrap();
+
+        let change = rules.parse("[cli] Hello world").expect("Failed to parse");
+
+        assert_eq!(change.scope, Some("cli"));
+        assert_eq!(change.description, "Hello world");
+    }
+
+    #[rstest]
+    #[case("Hello world")]
+    #[case("Hello :sparkles: world")]
+    fn returns_none_if_nothing_matches(#[case] message: &str) {
+        assert_eq!(gitmoji().parse(message), None);
+    }
+
+    #[test]
+    fn conventional_commits_have_precedence_over_fallbacks() {
+        let change = gitmoji()
+            .parse("fix: :sparkles: Hello world")
+            .expect("Failed to parse");
+
+        assert_eq!(change.type_, ChangeType::Fix);
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use crate::changelog::Scope;
+
+/// Normalization and display names of the changelog scopes
+///
+/// Aliases are resolved when changes are added to a [`ChangeLog`](crate::ChangeLog),
+/// so that all the spellings of a scope end up in the same group.
+/// Titles are used in place of the scope name when rendering the changelog.
+///
+/// When the case is ignored, the scopes that are neither an alias nor a configured scope are lower-cased,
+/// and the names differing only by case (scopes and aliases) must belong to the same scope.
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct ScopeNames {
+    aliases: HashMap<String, Scope>,
+    titles: HashMap<Scope, String>,
+    ignore_case: bool,
+}
+
+impl ScopeNames {
+    pub fn add_alias(&mut self, alias: impl Into<String>, scope: impl Into<Scope>) {
+        self.aliases.insert(alias.into(), scope.into());
+    }
+
+    pub fn set_title(&mut self, scope: impl Into<Scope>, title: impl Into<String>) {
+        self.titles.insert(scope.into(), title.into());
+    }
+
+    pub fn set_ignore_case(&mut self, ignore_case: bool) {
+        self.ignore_case = ignore_case;
+    }
+
+    /// Name of the group of the scope: the scope the alias stands for, or the scope itself
+    pub fn resolve(&self, scope: &str) -> Scope {
+        if !self.ignore_case {
+            return self
+                .aliases
+                .get(scope)
+                .cloned()
+                .unwrap_or_else(|| scope.to_owned());
+        }
+
+        self.aliases
+            .iter()
+            .find(|(alias, _)| alias.eq_ignore_ascii_case(scope))
+            .map(|(_, scope)| scope)
+            .or_else(|| {
+                self.aliases
+                    .values()
+                    .chain(self.titles.keys())
+                    .find(|it| it.eq_ignore_ascii_case(scope))
+            })
+            .cloned()
+            .unwrap_or_else(|| scope.to_lowercase())
+    }
+
+    pub(crate) fn title<'a>(&'a self, scope: &'a str) -> &'a str {
+        self.titles.get(scope).map_or(scope, String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_unknown_scope_to_itself() {
+        assert_eq!(ScopeNames::default().resolve("cli"), "cli");
+    }
+
+    #[test]
+    fn resolves_alias() {
+        let mut names = ScopeNames::default();
+        names.add_alias("command-line", "CLI");
+
+        assert_eq!(names.resolve("command-line"), "CLI");
+        assert_eq!(names.resolve("CLI"), "CLI");
+    }
+
+    #[test]
+    fn is_case_sensitive_by_default() {
+        let mut names = ScopeNames::default();
+        names.add_alias("cli", "CLI");
+
+        assert_eq!(names.resolve("Cli"), "Cli");
+        assert_eq!(names.resolve("Lib"), "Lib");
+    }
+
+    #[test]
+    fn can_ignore_case() {
+        let mut names = ScopeNames::default();
+        names.set_ignore_case(true);
+        names.add_alias("cli", "CLI");
+        names.set_title("Lib", "Library");
+
+        assert_eq!(names.resolve("Cli"), "CLI");
+        assert_eq!(names.resolve("cli"), "CLI");
+        assert_eq!(names.resolve("LIB"), "Lib");
+        assert_eq!(names.resolve("Core"), "core");
+    }
+
+    #[test]
+    fn title_defaults_to_scope() {
+        let mut names = ScopeNames::default();
+        names.set_title("CLI", "Command line interface");
+
+        assert_eq!(names.title("CLI"), "Command line interface");
+        assert_eq!(names.title("lib"), "lib");
+    }
+}
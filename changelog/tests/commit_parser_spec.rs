@@ -1,6 +1,6 @@
 use rstest::rstest;
 
-use autorel_chlg::{BreakingInfo, Change, ChangeLog, ChangeType, SemverScope};
+use autorel_chlg::{BreakingInfo, Change, ChangeLog, ChangeType, Footer, SemverScope};
 
 fn semver_scope_of(message: &str) -> Option<SemverScope> {
     let change = Change::parse_conventional_commit(message).expect("Not a conventional commit");
@@ -144,3 +144,37 @@ fn retain_breaking_change_description(#[case] message: &str, #[case] expected: B
 
     assert_eq!(expected, actual)
 }
+
+#[rstest]
+#[case("feat: hello", vec![])]
+#[case(
+    "feat: hello\n\nChangelog: skip",
+    vec![Footer { token: "Changelog", value: "skip" }]
+)]
+#[case(
+    "fix: hello\n\nwith a body\n\nRefs #42\nBREAKING CHANGE: oops",
+    vec![
+        Footer { token: "Refs", value: "42" },
+        Footer { token: "BREAKING CHANGE", value: "oops" },
+    ]
+)]
+fn retain_footers(#[case] message: &str, #[case] expected: Vec<Footer>) {
+    let actual = Change::parse_conventional_commit(message)
+        .expect("Failed to parse commit")
+        .footers;
+
+    assert_eq!(expected, actual)
+}
+
+#[rstest]
+#[case("feat: hello\n\nChangelog: skip\n", vec!["skip"])]
+#[case("feat: hello\n\nchangelog: skip", vec!["skip"])]
+#[case("feat: hello\n\nOther: value", vec![])]
+fn find_footer_values(#[case] message: &str, #[case] expected: Vec<&str>) {
+    let change = Change::parse_conventional_commit(message).expect("Failed to parse commit");
+
+    assert_eq!(
+        expected,
+        change.footer_values("Changelog").collect::<Vec<_>>()
+    )
+}
@@ -0,0 +1,287 @@
+use std::path::Path;
+
+use git2::{Oid, Repository, Signature};
+use tempfile::TempDir;
+
+use autorel_chlg::git::{
+    ChangeLogRepository, HistoryStrategy, LoadOptions, LoadedChangeLog, RELEASE_TRAILER,
+};
+use autorel_chlg::{ChangeFilter, ChangeLog, SemverScope};
+
+struct TestRepo {
+    dir: TempDir,
+    repo: Repository,
+}
+
+impl TestRepo {
+    fn new() -> Self {
+        let dir = tempfile::tempdir().expect("Failed to create directory");
+        let repo = Repository::init(dir.path()).expect("Failed to create repository");
+        Self { dir, repo }
+    }
+
+    fn commit(&self, message: &str) -> Oid {
+        self.commit_files(message, &[])
+    }
+
+    fn commit_files(&self, message: &str, files: &[&str]) -> Oid {
+        self.commit_as("Test", "test@example.com", message, files)
+    }
+
+    fn commit_as(&self, name: &str, email: &str, message: &str, files: &[&str]) -> Oid {
+        let mut index = self.repo.index().unwrap();
+        for file in files {
+            let path = self.dir.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, message).unwrap();
+            index.add_path(Path::new(file)).unwrap();
+        }
+        index.write().unwrap();
+
+        let signature = Signature::now(name, email).unwrap();
+        let tree = self.repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let parent = self
+            .repo
+            .head()
+            .ok()
+            .and_then(|head| head.peel_to_commit().ok());
+
+        self.repo
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                parent.iter().collect::<Vec<_>>().as_slice(),
+            )
+            .expect("Failed to commit")
+    }
+
+    /// Commits the tree of the first parent, updating `update_ref` if any
+    fn commit_with_parents(&self, update_ref: Option<&str>, message: &str, parents: &[Oid]) -> Oid {
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        let parents = parents
+            .iter()
+            .map(|oid| self.repo.find_commit(*oid).unwrap())
+            .collect::<Vec<_>>();
+        let tree = parents[0].tree().unwrap();
+
+        self.repo
+            .commit(
+                update_ref,
+                &signature,
+                &signature,
+                message,
+                &tree,
+                parents.iter().collect::<Vec<_>>().as_slice(),
+            )
+            .expect("Failed to commit")
+    }
+
+    fn tag(&self, name: &str, oid: Oid) {
+        self.repo
+            .tag_lightweight(name, &self.repo.find_object(oid, None).unwrap(), false)
+            .expect("Failed to tag");
+    }
+
+    fn load(&self, from: Option<&str>, to: &str) -> LoadedChangeLog {
+        self.load_with(from, to, &LoadOptions::default())
+    }
+
+    fn load_with(&self, from: Option<&str>, to: &str, options: &LoadOptions) -> LoadedChangeLog {
+        self.repo
+            .load_changelog_between(ChangeLog::default(), from, to, options)
+            .expect("Failed to load changelog")
+    }
+}
+
+#[test]
+fn load_between_revisions() {
+    let repo = TestRepo::new();
+    let first = repo.commit("chore: initial commit");
+    repo.tag("v1.0.0", first);
+    let fix = repo.commit("fix: Hello world");
+    repo.tag("v1.0.1", fix);
+    let feat = repo.commit("feat!: Breaking feature");
+    repo.commit("feat: Later feature");
+
+    let loaded = repo.load(Some("v1.0.0"), "v1.0.1");
+    assert_eq!(loaded.changelog.semver_scope(), Some(SemverScope::Fix));
+    assert_eq!(
+        loaded
+            .commits
+            .iter()
+            .map(|commit| commit.id.as_str())
+            .collect::<Vec<_>>(),
+        vec![fix.to_string()]
+    );
+
+    let loaded = repo.load(Some(&fix.to_string()), &feat.to_string());
+    assert_eq!(loaded.changelog.semver_scope(), Some(SemverScope::Breaking));
+    assert_eq!(loaded.commits.len(), 1);
+    assert_eq!(loaded.commits[0].message, "feat!: Breaking feature");
+
+    let loaded = repo.load(None, "HEAD~1");
+    assert_eq!(
+        loaded
+            .commits
+            .iter()
+            .map(|commit| commit.message.as_str())
+            .collect::<Vec<_>>(),
+        vec![
+            "chore: initial commit",
+            "fix: Hello world",
+            "feat!: Breaking feature"
+        ]
+    );
+}
+
+#[test]
+fn load_changelog_until_head() {
+    let repo = TestRepo::new();
+    let first = repo.commit("fix: initial commit");
+    repo.tag("v1.0.0", first);
+    repo.commit("feat: Hello world");
+
+    let changelog = repo
+        .repo
+        .load_changelog(Some("v1.0.0"))
+        .expect("Failed to load changelog");
+
+    assert_eq!(changelog.semver_scope(), Some(SemverScope::Feature));
+}
+
+#[test]
+fn credit_the_authors_of_the_changelog_entries() {
+    let repo = TestRepo::new();
+    std::fs::write(
+        repo.dir.path().join(".mailmap"),
+        "Jane Doe <jane@example.com> <jdoe@old.example.com>\n",
+    )
+    .unwrap();
+    repo.commit_as(
+        "jdoe",
+        "jdoe@old.example.com",
+        "feat: Hello world\n\nCo-authored-by: John Roe <john@example.com>",
+        &[],
+    );
+    repo.commit_as("Chore Author", "chore@example.com", "chore: Tidy up", &[]);
+    repo.commit_as("Wip Author", "wip@example.com", "Work in progress", &[]);
+    repo.commit_as("Docs Author", "docs@example.com", "fix(docs): Typo", &[]);
+
+    let mut filter = ChangeFilter::default();
+    filter.exclude_scope("docs");
+    let loaded = repo
+        .repo
+        .load_changelog_between(
+            ChangeLog::default().with_filter(filter),
+            None,
+            "HEAD",
+            &LoadOptions::default(),
+        )
+        .expect("Failed to load changelog");
+
+    assert_eq!(
+        loaded
+            .changelog
+            .contributors()
+            .iter()
+            .map(|it| (it.name.as_str(), it.email.as_str()))
+            .collect::<Vec<_>>(),
+        vec![
+            ("Jane Doe", "jane@example.com"),
+            ("John Roe", "john@example.com")
+        ]
+    );
+}
+
+#[test]
+fn revert_cancels_the_reverted_commit() {
+    let repo = TestRepo::new();
+    let first = repo.commit("chore: initial commit");
+    repo.tag("v1.0.0", first);
+    let feat = repo.commit("feat: Hello world");
+    repo.commit("fix: Typo");
+    repo.commit(&format!(
+        "Revert \"feat: Hello world\"\n\nThis reverts commit {}.",
+        feat
+    ));
+
+    let loaded = repo.load(Some("v1.0.0"), "HEAD");
+    assert_eq!(loaded.changelog.semver_scope(), Some(SemverScope::Fix));
+    assert!(loaded
+        .commits
+        .iter()
+        .all(|commit| commit.id != feat.to_string()));
+}
+
+#[test]
+fn load_merged_pull_requests() {
+    let repo = TestRepo::new();
+    let first = repo.commit("chore: initial commit");
+    repo.tag("v1.0.0", first);
+    let branch = repo.commit_with_parents(None, "fix: Branch fix", &[first]);
+    let branch = repo.commit_with_parents(None, "feat: Branch feature", &[branch]);
+    let fix = repo.commit("fix: Main fix");
+    let merge = repo.commit_with_parents(
+        Some("HEAD"),
+        "Merge pull request #12 from someone/branch\n\nfix: Pull request title",
+        &[fix, branch],
+    );
+
+    let load = |strategy| {
+        repo.load_with(
+            Some("v1.0.0"),
+            "HEAD",
+            &LoadOptions {
+                strategy,
+                ..LoadOptions::default()
+            },
+        )
+    };
+    let messages = |loaded: &LoadedChangeLog| {
+        loaded
+            .commits
+            .iter()
+            .map(|commit| commit.message.clone())
+            .collect::<Vec<_>>()
+    };
+
+    let loaded = load(HistoryStrategy::AllCommits);
+    assert_eq!(loaded.changelog.semver_scope(), Some(SemverScope::Feature));
+    assert_eq!(loaded.commits.len(), 3);
+
+    let loaded = load(HistoryStrategy::FirstParent);
+    assert_eq!(loaded.changelog.semver_scope(), Some(SemverScope::Fix));
+    assert_eq!(messages(&loaded), vec!["fix: Main fix"]);
+
+    let loaded = load(HistoryStrategy::MergeTitles);
+    assert_eq!(loaded.changelog.semver_scope(), Some(SemverScope::Fix));
+    assert_eq!(
+        messages(&loaded),
+        vec!["fix: Main fix", "fix: Pull request title (#12)"]
+    );
+    assert_eq!(loaded.commits[1].id, merge.to_string());
+}
+
+#[test]
+fn skip_release_commits() {
+    let repo = TestRepo::new();
+    let first = repo.commit("chore: initial commit");
+    repo.tag("v1.0.0", first);
+    let fix = repo.commit("fix: Hello world");
+    repo.commit(&format!("feat: Release v1.1.0\n\n{}", RELEASE_TRAILER));
+
+    let loaded = repo.load(Some("v1.0.0"), "HEAD");
+    assert_eq!(loaded.changelog.semver_scope(), Some(SemverScope::Fix));
+    assert_eq!(
+        loaded
+            .commits
+            .iter()
+            .map(|commit| commit.id.as_str())
+            .collect::<Vec<_>>(),
+        vec![fix.to_string()]
+    );
+    assert!(loaded.skipped.is_empty());
+}
@@ -1,4 +1,4 @@
-use autorel_chlg::{BreakingInfo, Change, ChangeLog, ChangeType};
+use autorel_chlg::{Bodies, BreakingInfo, Change, ChangeLog, ChangeType, Contributor, ScopeNames};
 
 #[test]
 fn markdown_example() {
@@ -10,6 +10,7 @@ fn markdown_example() {
         description: "Feature without scope",
         breaking: BreakingInfo::NotBreaking,
         body: None,
+        footers: Vec::new(),
     };
     changelog += Change {
         type_: ChangeType::Feature,
@@ -17,6 +18,7 @@ fn markdown_example() {
         description: "Feature with scope",
         breaking: BreakingInfo::NotBreaking,
         body: None,
+        footers: Vec::new(),
     };
     changelog += Change {
         type_: ChangeType::Fix,
@@ -24,6 +26,7 @@ fn markdown_example() {
         description: "Breaking fix",
         breaking: BreakingInfo::Breaking,
         body: None,
+        footers: Vec::new(),
     };
     changelog += Change {
         type_: ChangeType::Feature,
@@ -31,6 +34,7 @@ fn markdown_example() {
         description: "Breaking feature with more info",
         breaking: BreakingInfo::BreakingWithDescriptions(vec!["because!"]),
         body: None,
+        footers: Vec::new(),
     };
 
     let formated = format!("{}", changelog.markdown());
@@ -66,3 +70,194 @@ fn markdown_example() {
 "
     )
 }
+
+#[test]
+fn markdown_uses_scope_titles() {
+    let mut names = ScopeNames::default();
+    names.add_alias("cli", "CLI");
+    names.set_title("CLI", "Command line interface");
+
+    let changelog = ChangeLog::default().with_scope_names(names)
+        + Change {
+            scope: Some("cli"),
+            ..Change::new(ChangeType::Fix, "Fix with aliased scope")
+        }
+        + Change {
+            scope: Some("CLI"),
+            ..Change::new(ChangeType::Fix, "Fix with scope")
+        };
+
+    assert_eq!(
+        format!("{}", changelog.markdown()),
+        r"### Bug fixes
+
+#### Command line interface
+
+* Fix with aliased scope
+* Fix with scope
+
+
+"
+    )
+}
+
+#[test]
+fn markdown_with_bodies() {
+    let changelog = ChangeLog::default().with_bodies(Bodies::FeaturesOnly)
+        + Change {
+            body: Some("To migrate:\n\n* do this\n* do that"),
+            ..Change::new(ChangeType::Feature, "Feature with body")
+        }
+        + Change::new(ChangeType::Feature, "Feature without body")
+        + Change {
+            body: Some("Hidden body"),
+            ..Change::new(ChangeType::Fix, "Fix with body")
+        };
+
+    assert_eq!(
+        format!("{}", changelog.markdown()),
+        r"### Features
+
+* Feature with body
+
+  To migrate:
+
+  * do this
+  * do that
+
+* Feature without body
+
+
+### Bug fixes
+
+* Fix with body
+
+
+"
+    )
+}
+
+#[test]
+fn markdown_renders_body_once_per_commit() {
+    let changelog = ChangeLog::default().with_bodies(Bodies::BreakingChangesOnly)
+        + Change {
+            body: Some("Details of the change"),
+            breaking: BreakingInfo::BreakingWithDescriptions(vec![
+                "The `--force` flag is removed",
+                "The `--quiet` flag is removed",
+            ]),
+            ..Change::new(ChangeType::Feature, "Remove flags")
+        };
+
+    assert_eq!(
+        format!("{}", changelog.markdown()),
+        r"### Breaking changes
+
+* The `--force` flag is removed
+
+  Details of the change
+
+* The `--quiet` flag is removed
+
+
+### Features
+
+* Remove flags
+
+
+"
+    )
+}
+
+#[test]
+fn markdown_with_migration_guide() {
+    let mut changelog = ChangeLog::default().with_migration_guide(true);
+    changelog.add_commit(
+        "0123456789abcdef",
+        Change {
+            scope: Some("cli"),
+            breaking: BreakingInfo::BreakingWithDescriptions(vec![
+                "The `--force` flag is removed.\n\nUse `--yes` instead.",
+            ]),
+            ..Change::new(ChangeType::Feature, "Confirm with --yes")
+        },
+    );
+    changelog += Change {
+        breaking: BreakingInfo::BreakingWithDescriptions(vec!["Requires rust 1.56"]),
+        ..Change::new(ChangeType::Fix, "Update edition")
+    };
+
+    assert_eq!(
+        format!("{}", changelog.markdown()),
+        r"### Breaking changes
+
+* Update edition
+
+#### cli
+
+* Confirm with --yes
+
+
+### Migration guide
+
+#### cli: Confirm with --yes (0123456)
+
+The `--force` flag is removed.
+
+Use `--yes` instead.
+
+#### Update edition
+
+Requires rust 1.56
+
+
+### Features
+
+#### cli
+
+* Confirm with --yes
+
+
+### Bug fixes
+
+* Update edition
+
+
+"
+    )
+}
+
+#[test]
+fn markdown_with_contributors() {
+    let mut changelog =
+        ChangeLog::default().with_contributors(true) + Change::new(ChangeType::Fix, "Hello");
+    changelog.add_contributor(Contributor::new("Jane Doe", "jane@example.com"));
+    changelog.add_contributor(Contributor::new(
+        "John",
+        "42+johnny@users.noreply.github.com",
+    ));
+
+    assert_eq!(
+        format!("{}", changelog.markdown()),
+        r"### Bug fixes
+
+* Hello
+
+
+### Contributors
+
+* Jane Doe
+* @johnny
+
+
+"
+    )
+}
+
+#[test]
+fn markdown_without_contributors_by_default() {
+    let mut changelog = ChangeLog::default() + Change::new(ChangeType::Fix, "Hello");
+    changelog.add_contributor(Contributor::new("Jane Doe", "jane@example.com"));
+
+    assert!(!format!("{}", changelog.markdown()).contains("Contributors"));
+}
@@ -1,18 +1,73 @@
-use semver::Version;
+use semver::{Prerelease, Version};
 
 use autorel_chlg::SemverScope;
 
+use crate::cli::BumpLevel;
+
+/// How the versions are bumped before 1.0.0
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum PreStable {
+    /// Features bump the patch, and breaking changes bump the minor
+    #[default]
+    InitialDevelopment,
+
+    /// Same rules as after 1.0.0: features bump the minor, and breaking changes bump the major
+    Semver,
+}
+
 pub trait Bump: Sized {
     fn stabilize(&mut self);
-    fn bump(&mut self, scope: SemverScope);
+    fn bump(&mut self, scope: SemverScope, policy: PreStable);
+
+    /// Increments the component of the given level, regardless of the pre-1.0 rules
+    ///
+    /// Releasing a pre-release satisfies the bump of its own level (e.g. `1.2.4-beta.1` is bumped to `1.2.4` for a patch)
+    fn bump_level(&mut self, level: BumpLevel);
 
     #[inline]
-    fn bumped(mut self, scope: SemverScope) -> Self {
-        self.bump(scope);
+    fn bumped(mut self, scope: SemverScope, policy: PreStable) -> Self {
+        self.bump(scope, policy);
+        self
+    }
+
+    #[inline]
+    fn bumped_level(mut self, level: BumpLevel) -> Self {
+        self.bump_level(level);
         self
     }
 }
 
+/// Returns the pre-release of the version with the identifier (e.g. `beta.3`),
+/// numbered after the pre-releases of the same version already released
+pub fn prerelease(
+    version: &Version,
+    identifier: &str,
+    released: &[Version],
+) -> Result<Prerelease, semver::Error> {
+    let prefix = format!("{}.", identifier);
+    let number = released
+        .iter()
+        .filter(|it| {
+            (it.major, it.minor, it.patch) == (version.major, version.minor, version.patch)
+        })
+        .filter_map(|it| it.pre.as_str().strip_prefix(&prefix)?.parse::<u64>().ok())
+        .max()
+        .map_or(1, |it| it + 1);
+
+    Prerelease::new(&format!("{}{}", prefix, number))
+}
+
+/// Returns the level of the bump from one version to the other
+pub fn level(from: &Version, to: &Version) -> BumpLevel {
+    if to.major != from.major {
+        BumpLevel::Major
+    } else if to.minor != from.minor {
+        BumpLevel::Minor
+    } else {
+        BumpLevel::Patch
+    }
+}
+
 impl Bump for Version {
     fn stabilize(&mut self) {
         if self.major < 1 {
@@ -22,16 +77,38 @@ impl Bump for Version {
         }
     }
 
-    fn bump(&mut self, scope: SemverScope) {
-        match (self.major, scope) {
-            (0, SemverScope::Feature) | (_, SemverScope::Fix) => {
-                self.patch += 1;
+    fn bump(&mut self, scope: SemverScope, policy: PreStable) {
+        let initial_development = self.major == 0 && policy == PreStable::InitialDevelopment;
+        let level = match (initial_development, scope) {
+            (true, SemverScope::Feature) | (_, SemverScope::Fix) => BumpLevel::Patch,
+            (true, SemverScope::Breaking) | (_, SemverScope::Feature) => BumpLevel::Minor,
+            (_, SemverScope::Breaking) => BumpLevel::Major,
+        };
+
+        self.bump_level(level)
+    }
+
+    fn bump_level(&mut self, level: BumpLevel) {
+        if !self.pre.is_empty() {
+            // The release of a pre-release version already includes the bump of its own level
+            self.pre = Prerelease::EMPTY;
+            let included = match level {
+                BumpLevel::Patch => true,
+                BumpLevel::Minor => self.patch == 0,
+                BumpLevel::Major => self.minor == 0 && self.patch == 0,
+            };
+            if included {
+                return;
             }
-            (0, SemverScope::Breaking) | (_, SemverScope::Feature) => {
+        }
+
+        match level {
+            BumpLevel::Patch => self.patch += 1,
+            BumpLevel::Minor => {
                 self.minor += 1;
                 self.patch = 0;
             }
-            (_, SemverScope::Breaking) => {
+            BumpLevel::Major => {
                 self.major += 1;
                 self.minor = 0;
                 self.patch = 0;
@@ -65,7 +142,7 @@ mod tests {
     #[case("0.2.0", "0.3.0")]
     fn breaking_change(#[case] initial_version: &str, #[case] expected_target_version: &str) {
         let mut version: Version = initial_version.parse().unwrap();
-        version.bump(SemverScope::Breaking);
+        version.bump(SemverScope::Breaking, PreStable::InitialDevelopment);
         assert_eq!(version.to_string(), expected_target_version);
     }
 
@@ -75,7 +152,7 @@ mod tests {
     #[case("0.1.1", "0.1.2")]
     fn feature(#[case] initial_version: &str, #[case] expected_target_version: &str) {
         let mut version: Version = initial_version.parse().unwrap();
-        version.bump(SemverScope::Feature);
+        version.bump(SemverScope::Feature, PreStable::InitialDevelopment);
         assert_eq!(version.to_string(), expected_target_version);
     }
 
@@ -84,7 +161,87 @@ mod tests {
     #[case("0.1.2", "0.1.3")]
     fn fix(#[case] initial_version: &str, #[case] expected_target_version: &str) {
         let mut version: Version = initial_version.parse().unwrap();
-        version.bump(SemverScope::Fix);
+        version.bump(SemverScope::Fix, PreStable::InitialDevelopment);
+        assert_eq!(version.to_string(), expected_target_version);
+    }
+
+    #[rstest]
+    #[case("0.1.2", SemverScope::Fix, "0.1.3")]
+    #[case("0.1.2", SemverScope::Feature, "0.2.0")]
+    #[case("0.1.2", SemverScope::Breaking, "1.0.0")]
+    #[case("1.2.3", SemverScope::Breaking, "2.0.0")]
+    fn semver_policy(
+        #[case] initial_version: &str,
+        #[case] scope: SemverScope,
+        #[case] expected_target_version: &str,
+    ) {
+        let mut version: Version = initial_version.parse().unwrap();
+        version.bump(scope, PreStable::Semver);
+        assert_eq!(version.to_string(), expected_target_version);
+    }
+
+    #[rstest]
+    #[case("2.0.0-beta.1", SemverScope::Fix, "2.0.0")]
+    #[case("2.0.0-beta.1", SemverScope::Breaking, "2.0.0")]
+    #[case("1.3.0-beta.1", SemverScope::Feature, "1.3.0")]
+    #[case("1.3.0-beta.1", SemverScope::Breaking, "2.0.0")]
+    #[case("1.2.4-beta.1", SemverScope::Feature, "1.3.0")]
+    #[case("0.3.0-beta.1", SemverScope::Breaking, "0.3.0")]
+    fn bump_prerelease(
+        #[case] initial_version: &str,
+        #[case] scope: SemverScope,
+        #[case] expected_target_version: &str,
+    ) {
+        let mut version: Version = initial_version.parse().unwrap();
+        version.bump(scope, PreStable::InitialDevelopment);
+        assert_eq!(version.to_string(), expected_target_version);
+    }
+
+    #[rstest]
+    #[case("2.0.0", &[], "beta.1")]
+    #[case("2.0.0", &["1.0.0", "2.0.0-alpha.4", "1.9.0-beta.3"], "beta.1")]
+    #[case("2.0.0", &["2.0.0-beta.1", "2.0.0-beta.2", "2.0.0-alpha.4"], "beta.3")]
+    #[case("2.0.0", &["2.0.0-beta.9", "2.0.0-beta.10"], "beta.11")]
+    fn numbered_prerelease(
+        #[case] version: &str,
+        #[case] released: &[&str],
+        #[case] expected: &str,
+    ) {
+        let released: Vec<Version> = released.iter().map(|it| it.parse().unwrap()).collect();
+        let pre = prerelease(&version.parse().unwrap(), "beta", &released).unwrap();
+        assert_eq!(pre.as_str(), expected);
+    }
+
+    #[rstest]
+    #[case("1.2.3", "1.2.4", BumpLevel::Patch)]
+    #[case("1.2.3", "1.2.4-rc.1", BumpLevel::Patch)]
+    #[case("1.2.3", "1.3.0", BumpLevel::Minor)]
+    #[case("0.1.3", "0.2.0", BumpLevel::Minor)]
+    #[case("1.2.3", "2.0.0", BumpLevel::Major)]
+    fn level_between(#[case] from: &str, #[case] to: &str, #[case] expected: BumpLevel) {
+        assert_eq!(
+            level(&from.parse().unwrap(), &to.parse().unwrap()),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case("1.2.3", BumpLevel::Patch, "1.2.4")]
+    #[case("0.1.2", BumpLevel::Minor, "0.2.0")]
+    #[case("1.2.3", BumpLevel::Minor, "1.3.0")]
+    #[case("0.1.2", BumpLevel::Major, "1.0.0")]
+    #[case("1.2.4-beta.1", BumpLevel::Patch, "1.2.4")]
+    #[case("1.2.4-beta.1", BumpLevel::Minor, "1.3.0")]
+    #[case("1.3.0-beta.1", BumpLevel::Minor, "1.3.0")]
+    #[case("1.3.0-beta.1", BumpLevel::Major, "2.0.0")]
+    #[case("2.0.0-rc.1", BumpLevel::Major, "2.0.0")]
+    fn bump_level(
+        #[case] initial_version: &str,
+        #[case] level: BumpLevel,
+        #[case] expected_target_version: &str,
+    ) {
+        let mut version: Version = initial_version.parse().unwrap();
+        version.bump_level(level);
         assert_eq!(version.to_string(), expected_target_version);
     }
 }
@@ -0,0 +1,772 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use git2::{Oid, Repository};
+use semver::Version;
+use toml_edit::{DocumentMut, Item};
+
+use autorel_chlg::git::ChangeLogRepository;
+use autorel_chlg::SemverScope;
+
+use crate::bump::{Bump, PreStable};
+use crate::config::{CargoConfig, Config};
+use crate::version_file::{self, set_toml_string, toml_item, toml_item_mut, FileUpdate};
+
+const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// Manifests of a cargo workspace (or of a single package), the root manifest being the first one
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    pub manifests: Vec<Manifest>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Manifest {
+    pub path: PathBuf,
+    pub content: String,
+    document: DocumentMut,
+}
+
+impl Workspace {
+    pub fn load(path: &Path) -> Result<Self, version_file::Error> {
+        let root = Manifest::load(path.to_owned())?;
+        let members = root.workspace_members()?;
+        let mut manifests = vec![root];
+
+        for member_dir in members {
+            let path = member_dir.join("Cargo.toml");
+            if manifests.iter().all(|it| it.path != path) {
+                manifests.push(Manifest::load(path)?);
+            }
+        }
+
+        Ok(Self { manifests })
+    }
+
+    /// Updates setting the same version to all the packages of the workspace
+    pub fn uniform_updates(&self, version: &str) -> Vec<FileUpdate> {
+        let versions = self
+            .manifests
+            .iter()
+            .filter_map(Manifest::package_name)
+            .map(|name| (name, version))
+            .collect();
+        self.updates(&versions, Some(version))
+    }
+
+    /// Updates setting the versions of the packages (by name) and of the `workspace.package` table
+    pub fn updates(
+        &self,
+        versions: &HashMap<&str, &str>,
+        workspace_version: Option<&str>,
+    ) -> Vec<FileUpdate> {
+        self.manifests
+            .iter()
+            .filter_map(|manifest| {
+                let after = manifest.with_versions(versions, workspace_version);
+                if after == manifest.content {
+                    None
+                } else {
+                    Some(FileUpdate {
+                        path: manifest.path.clone(),
+                        before: manifest.content.clone(),
+                        after,
+                    })
+                }
+            })
+            .collect()
+    }
+}
+
+impl Manifest {
+    pub fn load(path: PathBuf) -> Result<Self, version_file::Error> {
+        let content = version_file::read(&path)?;
+        Self::parse(path, content)
+    }
+
+    pub fn parse(path: PathBuf, content: String) -> Result<Self, version_file::Error> {
+        match content.parse() {
+            Ok(document) => Ok(Self {
+                path,
+                content,
+                document,
+            }),
+            Err(err) => Err(version_file::Error::InvalidToml(path, err)),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        self.path.parent().unwrap_or_else(|| Path::new(""))
+    }
+
+    pub fn package_name(&self) -> Option<&str> {
+        self.document.get("package")?.get("name")?.as_str()
+    }
+
+    /// Version of the package (`None` if it is inherited from the workspace)
+    pub fn package_version(&self) -> Option<&str> {
+        self.document.get("package")?.get("version")?.as_str()
+    }
+
+    /// Whether the version of the package is inherited from the workspace (`version.workspace = true`)
+    pub fn inherits_version(&self) -> bool {
+        self.document
+            .get("package")
+            .and_then(|it| it.get("version"))
+            .and_then(|it| it.get("workspace"))
+            .and_then(Item::as_bool)
+            .unwrap_or(false)
+    }
+
+    /// Version of the `workspace.package` table
+    pub fn workspace_version(&self) -> Option<&str> {
+        self.document
+            .get("workspace")?
+            .get("package")?
+            .get("version")?
+            .as_str()
+    }
+
+    /// Names of the packages this package depends on by path
+    pub fn path_dependencies(&self) -> Vec<String> {
+        let mut dependencies: Vec<String> = dependency_tables(&self.document)
+            .into_iter()
+            .filter_map(|keys| toml_item(&self.document, &keys)?.as_table_like())
+            .flat_map(|table| table.iter())
+            .filter_map(|(name, definition)| {
+                let definition = definition.as_table_like()?;
+                definition.get("path")?;
+                Some(dependency_package(definition, name).to_owned())
+            })
+            .collect();
+        dependencies.sort_unstable();
+        dependencies.dedup();
+        dependencies
+    }
+
+    /// Directories of the workspace members, without the excluded ones
+    ///
+    /// Like cargo, the members may be glob patterns (e.g. `crates/*`).
+    fn workspace_members(&self) -> Result<Vec<PathBuf>, version_file::Error> {
+        let workspace = match self.document.get("workspace") {
+            None => return Ok(Vec::new()),
+            Some(workspace) => workspace,
+        };
+        let paths = |key: &str| -> Vec<&str> {
+            workspace
+                .get(key)
+                .and_then(Item::as_array)
+                .map(|array| array.iter().filter_map(|it| it.as_str()).collect())
+                .unwrap_or_default()
+        };
+        let excluded: Vec<PathBuf> = paths("exclude")
+            .into_iter()
+            .map(|it| self.dir().join(it))
+            .collect();
+
+        let mut members = Vec::new();
+        for member in paths("members") {
+            let path = self.dir().join(member);
+            if !member.contains(['*', '?', '[']) {
+                members.push(path);
+                continue;
+            }
+
+            let pattern = path.to_string_lossy();
+            let matches = glob::glob(&pattern).map_err(|err| {
+                version_file::Error::InvalidWorkspaceMember(self.path.clone(), member.into(), err)
+            })?;
+            members.extend(
+                matches
+                    .filter_map(Result::ok)
+                    .filter(|dir| dir.join("Cargo.toml").is_file()),
+            );
+        }
+
+        members.retain(|member| !excluded.iter().any(|it| member.starts_with(it)));
+        Ok(members)
+    }
+
+    /// Sets the version of the package and the version requirements of the path dependencies
+    fn with_versions(
+        &self,
+        versions: &HashMap<&str, &str>,
+        workspace_version: Option<&str>,
+    ) -> String {
+        let mut document = self.document.clone();
+
+        if let Some(version) = self.package_name().and_then(|name| versions.get(name)) {
+            if let Some(item) = toml_item_mut(&mut document, &["package", "version"]) {
+                set_toml_string(item, version);
+            }
+        }
+
+        if let Some(version) = workspace_version {
+            if let Some(item) = toml_item_mut(&mut document, &["workspace", "package", "version"]) {
+                set_toml_string(item, version);
+            }
+        }
+
+        for keys in dependency_tables(&self.document) {
+            let table = match toml_item_mut(&mut document, &keys).and_then(Item::as_table_like_mut)
+            {
+                None => continue,
+                Some(table) => table,
+            };
+            for (name, definition) in table.iter_mut() {
+                let definition = match definition.as_table_like_mut() {
+                    Some(definition) if definition.contains_key("path") => definition,
+                    _ => continue,
+                };
+                let version = versions.get(dependency_package(definition, &name));
+                if let (Some(version), Some(item)) = (version, definition.get_mut("version")) {
+                    if let Some(requirement) = item.as_str() {
+                        let operator = &requirement[..requirement.len()
+                            - requirement
+                                .trim_start_matches(['=', '^', '~', '<', '>', ' '])
+                                .len()];
+                        let requirement = format!("{}{}", operator, version);
+                        set_toml_string(item, &requirement);
+                    }
+                }
+            }
+        }
+
+        document.to_string()
+    }
+}
+
+/// Bumps the version of each package of the workspace according to the changes in its directory
+///
+/// The package at the root of the workspace (if any) gets the release version.
+/// Returns the paths of the updated files.
+pub fn release(
+    config: &Config,
+    cargo_config: &CargoConfig,
+    repo: &Repository,
+    from: Option<&str>,
+    version: &Version,
+    dry_run: bool,
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let changelog = config.changelog()?;
+    let options = config.load_options()?;
+    let policy = config.versioning.pre_stable.into();
+    let workspace = Workspace::load(&cargo_config.manifest)?;
+    let root_dir = repo_dir(repo, workspace.manifests[0].dir());
+
+    // Each released commit with the files it changes, to attribute its change to the members
+    let mut commits = Vec::new();
+    for commit in repo
+        .load_changelog_with(changelog.clone(), from, &options)?
+        .commits
+    {
+        let paths = changed_paths(repo, Oid::from_str(&commit.id)?)?;
+        commits.push((commit, paths));
+    }
+
+    let mut versions: HashMap<&str, Version> = HashMap::new();
+    for manifest in &workspace.manifests {
+        let (name, current) = match (manifest.package_name(), manifest.package_version()) {
+            (Some(name), Some(current)) => (name, current),
+            _ => continue,
+        };
+        let dir = repo_dir(repo, manifest.dir());
+        if dir == root_dir {
+            versions.insert(name, version.clone());
+            continue;
+        }
+
+        // The directories of the nested members belong to them
+        let nested: Vec<PathBuf> = workspace
+            .manifests
+            .iter()
+            .map(|it| repo_dir(repo, it.dir()))
+            .filter(|it| it != &dir && it.starts_with(&dir))
+            .collect();
+        let mut member_changelog = changelog.clone();
+        for (commit, paths) in &commits {
+            let touches = paths.iter().any(|path| {
+                path.starts_with(&dir) && !nested.iter().any(|it| path.starts_with(it))
+            });
+            if touches {
+                if let Ok(change) = options.rules.try_parse(&commit.message) {
+                    member_changelog.add_commit(&commit.id, change);
+                }
+            }
+        }
+        if let Some(scope) = member_changelog.semver_scope() {
+            versions.insert(name, current.parse::<Version>()?.bumped(scope, policy));
+        }
+    }
+
+    if cargo_config.cascade {
+        cascade(&workspace, &mut versions, policy)?;
+    }
+
+    for manifest in &workspace.manifests {
+        if let (Some(name), Some(current)) = (manifest.package_name(), manifest.package_version()) {
+            if let Some(version) = versions.get(name) {
+                println!("> {} {} -> {}", name, current, version);
+            }
+        }
+    }
+
+    let version_str = version.to_string();
+    let mut version_strings: HashMap<&str, String> = versions
+        .iter()
+        .map(|(name, version)| (*name, version.to_string()))
+        .collect();
+    let mut updates = workspace.updates(
+        &version_strings
+            .iter()
+            .map(|(name, version)| (*name, version.as_str()))
+            .collect(),
+        Some(&version_str),
+    );
+
+    let lock = workspace.manifests[0].dir().join("Cargo.lock");
+    let lock_update = if lock.is_file() {
+        let has_workspace_version = workspace.manifests[0].workspace_version().is_some();
+        for manifest in &workspace.manifests {
+            if let Some(name) = manifest.package_name() {
+                if manifest.inherits_version() && has_workspace_version {
+                    version_strings.insert(name, version_str.clone());
+                }
+            }
+        }
+        lock_update(&lock, &version_strings)?
+    } else {
+        None
+    };
+    let lock_is_committed = lock_update.is_some() && !repo.is_path_ignored(&lock)?;
+    updates.extend(lock_update);
+
+    let mut updated = version_file::apply(updates, dry_run)?;
+    if !lock_is_committed {
+        updated.retain(|it| it != &lock);
+    }
+
+    Ok(updated)
+}
+
+/// Sets the versions of the packages of the workspace in the lock file
+///
+/// Unlike `cargo update`, the dependencies are not resolved again.
+/// The packages of the workspace are the ones without `source`.
+fn lock_update(
+    path: &Path,
+    versions: &HashMap<&str, String>,
+) -> Result<Option<FileUpdate>, version_file::Error> {
+    let before = version_file::read(path)?;
+    let mut document: DocumentMut = before
+        .parse()
+        .map_err(|err| version_file::Error::InvalidToml(path.to_owned(), err))?;
+
+    let packages = match document
+        .get_mut("package")
+        .and_then(Item::as_array_of_tables_mut)
+    {
+        None => return Ok(None),
+        Some(packages) => packages,
+    };
+
+    let mut previous: HashMap<String, String> = HashMap::new();
+    for package in packages.iter_mut() {
+        if package.contains_key("source") {
+            continue;
+        }
+        let name = match package.get("name").and_then(Item::as_str) {
+            None => continue,
+            Some(name) => name.to_owned(),
+        };
+        if let (Some(version), Some(item)) =
+            (versions.get(name.as_str()), package.get_mut("version"))
+        {
+            if let Some(current) = item.as_str() {
+                previous.insert(name, current.to_owned());
+            }
+            set_toml_string(item, version);
+        }
+    }
+
+    // The dependencies are referenced as "name version" when several versions of a package are locked
+    for package in packages.iter_mut() {
+        let dependencies = match package.get_mut("dependencies").and_then(Item::as_array_mut) {
+            None => continue,
+            Some(dependencies) => dependencies,
+        };
+        for dependency in dependencies.iter_mut() {
+            let reference = match dependency.as_str().and_then(|it| it.split_once(' ')) {
+                None => continue,
+                Some((name, version))
+                    if previous.get(name).map(String::as_str) == Some(version) =>
+                {
+                    format!("{} {}", name, versions[name])
+                }
+                Some(_) => continue,
+            };
+            let decor = dependency.decor().clone();
+            *dependency = reference.into();
+            *dependency.decor_mut() = decor;
+        }
+    }
+
+    let after = document.to_string();
+    if after == before {
+        return Ok(None);
+    }
+    Ok(Some(FileUpdate {
+        path: path.to_owned(),
+        before,
+        after,
+    }))
+}
+
+/// Bumps the patch version of the packages depending on a released package (transitively)
+fn cascade<'a>(
+    workspace: &'a Workspace,
+    versions: &mut HashMap<&'a str, Version>,
+    policy: PreStable,
+) -> Result<(), semver::Error> {
+    loop {
+        let mut released = false;
+        for manifest in &workspace.manifests {
+            if let (Some(name), Some(current)) =
+                (manifest.package_name(), manifest.package_version())
+            {
+                let depends_on_released = manifest
+                    .path_dependencies()
+                    .iter()
+                    .any(|dependency| versions.contains_key(dependency.as_str()));
+
+                if !versions.contains_key(name) && depends_on_released {
+                    versions.insert(
+                        name,
+                        current.parse::<Version>()?.bumped(SemverScope::Fix, policy),
+                    );
+                    released = true;
+                }
+            }
+        }
+        if !released {
+            return Ok(());
+        }
+    }
+}
+
+/// Paths of the files changed by the commit (compared to its first parent), relative to the root of the repository
+fn changed_paths(repo: &Repository, id: Oid) -> Result<Vec<PathBuf>, git2::Error> {
+    let commit = repo.find_commit(id)?;
+    let parent_tree = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree()?),
+        Err(_) => None,
+    };
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+
+    Ok(diff
+        .deltas()
+        .flat_map(|delta| [delta.old_file().path(), delta.new_file().path()])
+        .flatten()
+        .map(Path::to_path_buf)
+        .collect())
+}
+
+/// Directory relative to the root of the repository, like the paths of the changes
+fn repo_dir(repo: &Repository, dir: &Path) -> PathBuf {
+    let dir: PathBuf = dir
+        .components()
+        .filter(|it| !matches!(it, std::path::Component::CurDir))
+        .collect();
+    match repo.workdir().map(|workdir| dir.strip_prefix(workdir)) {
+        Some(Ok(relative)) => relative.to_owned(),
+        _ => dir,
+    }
+}
+
+/// Version of the package, or of the workspace if the manifest has no package version
+pub fn manifest_version(content: &str) -> Option<String> {
+    let manifest = Manifest::parse(PathBuf::new(), content.to_owned()).ok()?;
+    manifest
+        .package_version()
+        .or_else(|| manifest.workspace_version())
+        .map(String::from)
+}
+
+/// Keys of the dependency tables, including the platform-specific and the workspace ones
+fn dependency_tables(document: &DocumentMut) -> Vec<Vec<String>> {
+    let mut tables: Vec<Vec<String>> = DEPENDENCY_TABLES
+        .iter()
+        .map(|table| vec![String::from(*table)])
+        .collect();
+    tables.push(vec![
+        String::from("workspace"),
+        String::from("dependencies"),
+    ]);
+
+    if let Some(targets) = document.get("target").and_then(Item::as_table_like) {
+        for (target, _) in targets.iter() {
+            tables.extend(DEPENDENCY_TABLES.iter().map(|table| {
+                vec![
+                    String::from("target"),
+                    String::from(target),
+                    String::from(*table),
+                ]
+            }));
+        }
+    }
+
+    tables
+}
+
+/// Name of the package of the dependency, which may be renamed with the `package` key
+fn dependency_package<'a>(definition: &'a dyn toml_edit::TableLike, name: &'a str) -> &'a str {
+    definition
+        .get("package")
+        .and_then(Item::as_str)
+        .unwrap_or(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    const MANIFEST: &str = r#"[workspace]
+members = ["changelog"]
+
+[package]
+name = "autorel" # the binary
+version = "0.1.5"
+edition = "2018"
+
+[dependencies]
+autorel_chlg = { path = "changelog", version = "=0.1.5" }
+semver = { version = "1.0" }
+other = { path = "../other", version = "0.2" }
+renamed = { package = "autorel_chlg", version = "0.1", path = "changelog" }
+
+[dev-dependencies.autorel_chlg]
+version = "0.1.5" # keep aligned
+path = "changelog"
+
+[dev-dependencies.rstest]
+version = "0.11.0"
+
+[target.'cfg(unix)'.dependencies]
+autorel_chlg = { path = "changelog", version = "0.1.5" }
+"#;
+
+    fn manifest(content: &str) -> Manifest {
+        Manifest::parse(PathBuf::from("Cargo.toml"), String::from(content))
+            .expect("Invalid manifest")
+    }
+
+    #[test]
+    fn set_versions() {
+        let versions = vec![("autorel", "1.0.0"), ("autorel_chlg", "0.2.0")]
+            .into_iter()
+            .collect();
+
+        assert_eq!(
+            manifest(MANIFEST).with_versions(&versions, None),
+            r#"[workspace]
+members = ["changelog"]
+
+[package]
+name = "autorel" # the binary
+version = "1.0.0"
+edition = "2018"
+
+[dependencies]
+autorel_chlg = { path = "changelog", version = "=0.2.0" }
+semver = { version = "1.0" }
+other = { path = "../other", version = "0.2" }
+renamed = { package = "autorel_chlg", version = "0.2.0", path = "changelog" }
+
+[dev-dependencies.autorel_chlg]
+version = "0.2.0" # keep aligned
+path = "changelog"
+
+[dev-dependencies.rstest]
+version = "0.11.0"
+
+[target.'cfg(unix)'.dependencies]
+autorel_chlg = { path = "changelog", version = "0.2.0" }
+"#
+        );
+    }
+
+    #[test]
+    fn set_workspace_version() {
+        let content = "[workspace.package]\nversion = \"0.1.0\"\n\n[package]\nname = \"a\"\nversion.workspace = true\n";
+
+        assert_eq!(
+            manifest(content).with_versions(&HashMap::new(), Some("0.2.0")),
+            "[workspace.package]\nversion = \"0.2.0\"\n\n[package]\nname = \"a\"\nversion.workspace = true\n"
+        );
+        assert!(manifest(content).inherits_version());
+    }
+
+    #[test]
+    fn ignore_versions_in_strings_and_multiline_values() {
+        let content = "[package]\nname = \"a\"\ndescription = \"\"\"\nversion = \"0.0.1\"\n\"\"\"\nversion = \"0.1.0\"\n";
+
+        assert_eq!(
+            manifest(content).with_versions(&vec![("a", "0.2.0")].into_iter().collect(), None),
+            "[package]\nname = \"a\"\ndescription = \"\"\"\nversion = \"0.0.1\"\n\"\"\"\nversion = \"0.2.0\"\n"
+        );
+    }
+
+    #[test]
+    fn read_manifest() {
+        let manifest = manifest(MANIFEST);
+
+        assert_eq!(manifest.package_name(), Some("autorel"));
+        assert_eq!(manifest.package_version(), Some("0.1.5"));
+        assert_eq!(manifest.path_dependencies(), vec!["autorel_chlg", "other"]);
+        assert_eq!(
+            manifest_version("package.version = \"1.2.3\"\npackage.name = \"a\"\n").as_deref(),
+            Some("1.2.3")
+        );
+    }
+
+    #[test]
+    fn load_workspace_members() {
+        let dir = tempfile::tempdir().expect("Failed to create directory");
+        let write = |path: &str, content: &str| {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write(
+            "Cargo.toml",
+            "[workspace]\nmembers = [\n    \"a\", # first\n    \"crates/*\",\n]\nexclude = [\"crates/excluded\"]\n",
+        );
+        write("a/Cargo.toml", "[package]\nname = \"a\"\n");
+        write("crates/b/Cargo.toml", "[package]\nname = \"b\"\n");
+        write(
+            "crates/excluded/Cargo.toml",
+            "[package]\nname = \"excluded\"\n",
+        );
+        write("crates/not-a-crate/README.md", "");
+
+        let workspace = Workspace::load(&dir.path().join("Cargo.toml")).expect("Failed to load");
+
+        assert_eq!(
+            workspace
+                .manifests
+                .iter()
+                .map(Manifest::package_name)
+                .collect::<Vec<_>>(),
+            vec![None, Some("a"), Some("b")]
+        );
+    }
+
+    #[test]
+    fn update_lock_file() {
+        let dir = tempfile::tempdir().expect("Failed to create directory");
+        let path = dir.path().join("Cargo.lock");
+        fs::write(
+            &path,
+            r#"version = 3
+
+[[package]]
+name = "a"
+version = "0.1.0"
+dependencies = [
+ "b",
+ "c 0.1.0",
+]
+
+[[package]]
+name = "b"
+version = "1.0.0"
+
+[[package]]
+name = "c"
+version = "0.1.0"
+
+[[package]]
+name = "c"
+version = "0.1.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#,
+        )
+        .unwrap();
+        let versions = vec![("a", String::from("0.2.0")), ("c", String::from("0.1.1"))]
+            .into_iter()
+            .collect();
+
+        let update = lock_update(&path, &versions)
+            .expect("Failed to read lock file")
+            .expect("No update");
+
+        assert_eq!(
+            update.after,
+            r#"version = 3
+
+[[package]]
+name = "a"
+version = "0.2.0"
+dependencies = [
+ "b",
+ "c 0.1.1",
+]
+
+[[package]]
+name = "b"
+version = "1.0.0"
+
+[[package]]
+name = "c"
+version = "0.1.1"
+
+[[package]]
+name = "c"
+version = "0.1.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#
+        );
+    }
+
+    #[test]
+    fn cascade_patch_bumps_to_dependents() {
+        let manifest = |name: &str, dependency: Option<&str>| {
+            Manifest::parse(
+                PathBuf::from(name).join("Cargo.toml"),
+                format!(
+                    "[package]\nname = \"{}\"\nversion = \"1.0.0\"\n\n[dependencies]\n{}",
+                    name,
+                    dependency
+                        .map(|it| format!("{} = {{ path = \"../{}\" }}\n", it, it))
+                        .unwrap_or_default()
+                ),
+            )
+            .expect("Invalid manifest")
+        };
+        let workspace = Workspace {
+            manifests: vec![
+                manifest("core", None),
+                manifest("lib", Some("core")),
+                manifest("cli", Some("lib")),
+                manifest("other", None),
+            ],
+        };
+        let mut versions = vec![("core", Version::new(1, 1, 0))].into_iter().collect();
+
+        cascade(&workspace, &mut versions, PreStable::default()).expect("Invalid version");
+
+        assert_eq!(
+            versions,
+            vec![
+                ("core", Version::new(1, 1, 0)),
+                ("lib", Version::new(1, 0, 1)),
+                ("cli", Version::new(1, 0, 1)),
+            ]
+            .into_iter()
+            .collect()
+        );
+    }
+}
@@ -1,6 +1,8 @@
 use std::path::PathBuf;
 
-use clap::{crate_authors, crate_version, AppSettings, Clap};
+use clap::{crate_authors, crate_version, AppSettings, ArgEnum, Clap};
+use semver::Version;
+use serde_derive::Deserialize;
 
 /// Given a git repository that follows conventional-commits convention,
 /// `autorel` parses the commit messages since the last version tag to decide if there is something to release.
@@ -35,6 +37,7 @@ use clap::{crate_authors, crate_version, AppSettings, Clap};
 pub struct Opts {
     /// Only prints what would be done if the this flag wasn't specified.
     /// Without actually doing anything.
+    /// Also prints why the commits that are not part of the changelog couldn't be parsed.
     #[clap(long)]
     pub dry_run: bool,
 
@@ -46,9 +49,58 @@ pub struct Opts {
     #[clap(long)]
     pub force: bool,
 
+    /// Version of the previous release, to use instead of the latest version found in the tags
+    ///
+    /// The changes are computed since the tag of this version if it exists,
+    /// since the commit that wrote it in the `baseline` file if configured, or else from the beginning of the history.
+    #[clap(long)]
+    pub from_version: Option<Version>,
+
     /// Ensure to release a stable version number (>= 1.0.0)
     #[clap(long)]
     pub stable: bool,
+
+    /// Release this version instead of the computed one. It must be greater than the previous version.
+    ///
+    /// Takes precedence over `--stable`, `--bump` and the `Release-As` footers,
+    /// and implies a release even if there is no feature or fix.
+    #[clap(long, conflicts_with_all = &["stable", "bump"])]
+    pub release_as: Option<Version>,
+
+    /// Minimum level of the version bump, regardless of the changes
+    ///
+    /// Fails if there is no previous version to bump.
+    #[clap(long, arg_enum)]
+    pub bump: Option<BumpLevel>,
+
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(ArgEnum, Deserialize, Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+#[serde(rename_all = "kebab-case")]
+pub enum BumpLevel {
+    Patch,
+    Minor,
+    Major,
+}
+
+#[derive(Clap)]
+pub enum Command {
+    /// Verify that commit messages follow the conventional commits convention
+    ///
+    /// Can be used as a git `commit-msg` hook, or to verify the commits of a branch before merging it.
+    Lint(LintOpts),
+}
+
+#[derive(Clap)]
+pub struct LintOpts {
+    /// File containing the commit message to verify. Reads the standard input if absent
+    pub file: Option<PathBuf>,
+
+    /// Verify all the commits of a git revision range instead (e.g. `main..HEAD`)
+    #[clap(long, conflicts_with = "file")]
+    pub range: Option<String>,
 }
 
 pub fn parse() -> Opts {
@@ -57,6 +109,9 @@ pub fn parse() -> Opts {
 
 #[cfg(test)]
 mod tests {
+    use clap::ErrorKind;
+    use rstest::rstest;
+
     use super::Clap;
     use super::*;
 
@@ -122,4 +177,120 @@ mod tests {
 
         assert!(opts.stable);
     }
+
+    #[test]
+    fn no_previous_version_by_default() {
+        let opts = Opts::try_parse_from(vec!["autorel"]).expect("Failed to parse command line");
+
+        assert_eq!(opts.from_version, None);
+    }
+
+    #[test]
+    fn previous_version_can_be_given() {
+        let opts = Opts::try_parse_from(vec!["autorel", "--from-version", "1.4.2"])
+            .expect("Failed to parse command line");
+
+        assert_eq!(opts.from_version, Some(Version::new(1, 4, 2)));
+    }
+
+    #[test]
+    fn previous_version_must_be_valid() {
+        assert!(Opts::try_parse_from(vec!["autorel", "--from-version", "1.4"]).is_err());
+    }
+
+    #[rstest]
+    #[case(vec!["autorel", "--version"])]
+    #[case(vec!["autorel", "-V"])]
+    fn print_version(#[case] args: Vec<&str>) {
+        let kind = Opts::try_parse_from(args).err().map(|it| it.kind);
+
+        assert_eq!(kind, Some(ErrorKind::DisplayVersion));
+    }
+
+    #[test]
+    fn version_can_be_forced() {
+        let opts = Opts::try_parse_from(vec!["autorel", "--release-as", "2.0.0"])
+            .expect("Failed to parse command line");
+
+        assert_eq!(opts.release_as, Some(Version::new(2, 0, 0)));
+    }
+
+    #[rstest]
+    #[case("--stable")]
+    #[case("--bump=major")]
+    fn forced_version_conflicts_with_computation_options(#[case] option: &str) {
+        assert!(Opts::try_parse_from(vec!["autorel", "--release-as", "2.0.0", option]).is_err());
+    }
+
+    #[test]
+    fn no_minimum_bump_by_default() {
+        let opts = Opts::try_parse_from(vec!["autorel"]).expect("Failed to parse command line");
+
+        assert_eq!(opts.release_as, None);
+        assert_eq!(opts.bump, None);
+    }
+
+    #[rstest]
+    #[case("patch", BumpLevel::Patch)]
+    #[case("minor", BumpLevel::Minor)]
+    #[case("major", BumpLevel::Major)]
+    fn minimum_bump(#[case] level: &str, #[case] expected: BumpLevel) {
+        let opts = Opts::try_parse_from(vec!["autorel", "--bump", level])
+            .expect("Failed to parse command line");
+
+        assert_eq!(opts.bump, Some(expected));
+    }
+
+    #[test]
+    fn release_by_default() {
+        let opts = Opts::try_parse_from(vec!["autorel"]).expect("Failed to parse command line");
+
+        assert!(opts.command.is_none());
+    }
+
+    #[test]
+    fn lint_standard_input() {
+        let opts =
+            Opts::try_parse_from(vec!["autorel", "lint"]).expect("Failed to parse command line");
+
+        match opts.command {
+            Some(Command::Lint(lint)) => {
+                assert_eq!(lint.file, None);
+                assert_eq!(lint.range, None);
+            }
+            None => panic!("Lint command not recognized"),
+        }
+    }
+
+    #[test]
+    fn lint_file() {
+        let opts = Opts::try_parse_from(vec!["autorel", "lint", ".git/COMMIT_EDITMSG"])
+            .expect("Failed to parse command line");
+
+        match opts.command {
+            Some(Command::Lint(lint)) => {
+                assert_eq!(lint.file, Some(PathBuf::from(".git/COMMIT_EDITMSG")))
+            }
+            None => panic!("Lint command not recognized"),
+        }
+    }
+
+    #[test]
+    fn lint_range() {
+        let opts = Opts::try_parse_from(vec!["autorel", "lint", "--range", "main..HEAD"])
+            .expect("Failed to parse command line");
+
+        match opts.command {
+            Some(Command::Lint(lint)) => assert_eq!(lint.range, Some(String::from("main..HEAD"))),
+            None => panic!("Lint command not recognized"),
+        }
+    }
+
+    #[test]
+    fn cannot_lint_file_and_range() {
+        assert!(
+            Opts::try_parse_from(vec!["autorel", "lint", "file.txt", "--range", "main..HEAD"])
+                .is_err()
+        );
+    }
 }
@@ -33,13 +33,17 @@ impl std::error::Error for Error {}
 pub fn execute_all(
     cmds: &[impl AsRef<str>],
     version: impl AsRef<str>,
+    channel: &str,
     dry_run: bool,
 ) -> Result<(), Error> {
     let mut shell = Command::new("sh").stdin(Stdio::piped()).spawn()?;
     let mut stdin = shell.stdin.take().ok_or(Error::CannotRunCmd(None))?;
 
     for cmd in cmds {
-        let mut cmd = cmd.as_ref().replace("{{version}}", version.as_ref());
+        let mut cmd = cmd
+            .as_ref()
+            .replace("{{version}}", version.as_ref())
+            .replace("{{channel}}", channel);
 
         stdin.write_all(format!("echo \"> {}\"\n", cmd.replace('"', "\\\"")).as_bytes())?;
 
@@ -1,10 +1,20 @@
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::{self, Debug, Display, Formatter};
 use std::fs::File;
 use std::io::{self, Read};
 use std::path::{Path, PathBuf};
 
+use semver::{BuildMetadata, Version};
 use serde_derive::Deserialize;
 
+use crate::bump::PreStable;
+use crate::cli::BumpLevel;
+use crate::sign::SignFormat;
+use crate::version_file::{self, VersionFile};
+use autorel_chlg::git::{HistoryStrategy, LoadOptions};
+
+use autorel_chlg::{Bodies, ChangeFilter, ChangeLog, ChangeType, ParsingRules, ScopeNames};
+
 pub fn read(path: &Path) -> Result<Config, Error> {
     do_read(path).map_err(|cause| Error {
         path: path.into(),
@@ -20,6 +30,32 @@ fn parse(data: impl Read) -> Result<Config, Cause> {
     let mut result: Config =
         serde_yaml::from_reader(data).map_err(|err| Cause::InvalidConfig(Box::new(err)))?;
 
+    result
+        .change_filter()
+        .map_err(|err| Cause::InvalidConfig(Box::new(err)))?;
+    result
+        .load_options()
+        .map_err(|err| Cause::InvalidConfig(Box::new(err)))?;
+    let version_files = result
+        .version_files()
+        .map_err(|err| Cause::InvalidConfig(Box::new(err)))?;
+    if result.cargo.is_some() {
+        if let Some(VersionFile::Cargo(path)) = version_files
+            .into_iter()
+            .find(|it| matches!(it, VersionFile::Cargo(_)))
+        {
+            return Err(Cause::InvalidConfig(Box::new(
+                version_file::Error::ConflictsWithCargo(path),
+            )));
+        }
+    }
+    result
+        .baseline()
+        .map_err(|err| Cause::InvalidConfig(Box::new(err)))?;
+    result
+        .check_scope_names()
+        .map_err(|err| Cause::InvalidConfig(Box::new(err)))?;
+
     if result.changelog {
         result.commit.files.push(PathBuf::from("CHANGELOG.md"));
     }
@@ -43,9 +79,149 @@ pub struct Config {
 
     #[serde(default)]
     pub commit: CommitConfig,
+
+    #[serde(default)]
+    pub git: GitConfig,
+
+    #[serde(default)]
+    pub scopes: HashMap<String, ScopeConfig>,
+
+    #[serde(default)]
+    pub exclude: ExcludeConfig,
+
+    #[serde(default)]
+    pub history: History,
+
+    #[serde(default)]
+    pub parsing: ParsingConfig,
+
+    #[serde(default)]
+    pub lint: LintConfig,
+
+    #[serde(default)]
+    pub notes: NotesConfig,
+
+    #[serde(default)]
+    pub version_files: Vec<VersionFileConfig>,
+
+    #[serde(default)]
+    pub cargo: Option<CargoConfig>,
+
+    #[serde(default)]
+    pub baseline: Option<VersionFileConfig>,
+
+    #[serde(default)]
+    pub versioning: VersioningConfig,
+
+    #[serde(default)]
+    pub branches: Vec<BranchConfig>,
 }
 
 impl Config {
+    pub fn scope_names(&self) -> ScopeNames {
+        let mut names = ScopeNames::default();
+        names.set_ignore_case(self.parsing.ignore_scope_case);
+        for (scope, config) in &self.scopes {
+            if let Some(title) = &config.title {
+                names.set_title(scope, title);
+            }
+            for alias in &config.aliases {
+                names.add_alias(alias, scope);
+            }
+        }
+        names
+    }
+
+    /// When the scope case is ignored, verifies that the names differing only by case (scopes and aliases)
+    /// belong to the same scope, otherwise the scope of a change would be ambiguous
+    fn check_scope_names(&self) -> Result<(), AmbiguousScope> {
+        if !self.parsing.ignore_scope_case {
+            return Ok(());
+        }
+
+        let mut owners: HashMap<String, &str> = HashMap::new();
+        for (scope, config) in self.scopes.iter().collect::<BTreeMap<_, _>>() {
+            for name in std::iter::once(scope).chain(&config.aliases) {
+                if let Some(other) = owners.insert(name.to_lowercase(), scope) {
+                    if other != scope {
+                        return Err(AmbiguousScope {
+                            name: name.clone(),
+                            scopes: (other.to_owned(), scope.clone()),
+                        });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Empty changelog, grouping and filtering the changes as configured
+    pub fn changelog(&self) -> Result<ChangeLog, regex::Error> {
+        Ok(ChangeLog::default()
+            .with_scope_names(self.scope_names())
+            .with_filter(self.change_filter()?))
+    }
+
+    /// Settings of the first entry of `branches` matching the branch name
+    pub fn branch(&self, name: &str) -> Option<&BranchConfig> {
+        self.branches.iter().find(|branch| branch.matches(name))
+    }
+
+    /// Name of the tag of the given version
+    pub fn tag_name(&self, version: &Version) -> String {
+        if self.versioning.tag_metadata || version.build.is_empty() {
+            format!("{}{}", self.tag_prefix, version)
+        } else {
+            let mut version = version.clone();
+            version.build = BuildMetadata::EMPTY;
+            format!("{}{}", self.tag_prefix, version)
+        }
+    }
+
+    pub fn load_options(&self) -> Result<LoadOptions, regex::Error> {
+        let mut rules = ParsingRules::default();
+        for (alias, kind) in &self.parsing.type_aliases {
+            rules.add_type_alias(alias, (*kind).into());
+        }
+        for fallback in &self.parsing.fallbacks {
+            rules.add_fallback(&fallback.pattern, fallback.type_.into(), fallback.breaking)?;
+        }
+
+        Ok(LoadOptions {
+            strategy: self.history.into(),
+            rules,
+        })
+    }
+
+    pub fn version_files(&self) -> Result<Vec<VersionFile>, version_file::Error> {
+        self.version_files
+            .iter()
+            .map(|file| VersionFile::new(file.path.clone(), file.pattern.as_deref()))
+            .collect()
+    }
+
+    /// File from which the previous version is read when it cannot be found in the tags
+    pub fn baseline(&self) -> Result<Option<VersionFile>, version_file::Error> {
+        self.baseline
+            .as_ref()
+            .map(|file| VersionFile::new(file.path.clone(), file.pattern.as_deref()))
+            .transpose()
+    }
+
+    pub fn change_filter(&self) -> Result<ChangeFilter, regex::Error> {
+        let mut filter = ChangeFilter::default();
+        for scope in &self.exclude.scopes {
+            filter.exclude_scope(scope);
+        }
+        for pattern in &self.exclude.descriptions {
+            filter.exclude_description(pattern)?;
+        }
+        for pattern in &self.exclude.contributors {
+            filter.exclude_contributor(pattern)?;
+        }
+        Ok(filter)
+    }
+
     #[inline]
     fn default_changelog() -> bool {
         true
@@ -65,6 +241,73 @@ pub struct GithubConfig {
     pub files: Vec<PathBuf>,
 }
 
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+pub struct GitConfig {
+    /// Remote to which the release commit and tag are pushed
+    #[serde(default = "GitConfig::default_remote")]
+    pub remote: String,
+
+    /// Environment variable containing the token to push over https
+    #[serde(default = "GitConfig::default_token_env")]
+    pub token_env: String,
+
+    /// Branch of the remote to which the release commit is pushed (the current branch by default)
+    #[serde(default)]
+    pub push_branch: Option<String>,
+
+    /// Whether the release commit and tag are pushed
+    #[serde(default = "GitConfig::default_push")]
+    pub push: bool,
+
+    #[serde(default)]
+    pub sign: SignConfig,
+}
+
+/// Signature of the release commit and tag. The unset options are read from the git configuration.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Default)]
+pub struct SignConfig {
+    #[serde(default)]
+    pub commits: Option<bool>,
+
+    #[serde(default)]
+    pub tags: Option<bool>,
+
+    #[serde(default)]
+    pub format: Option<SignFormat>,
+
+    #[serde(default)]
+    pub key: Option<String>,
+}
+
+impl GitConfig {
+    #[inline]
+    fn default_remote() -> String {
+        String::from("origin")
+    }
+
+    #[inline]
+    fn default_token_env() -> String {
+        String::from("GITHUB_TOKEN")
+    }
+
+    #[inline]
+    fn default_push() -> bool {
+        true
+    }
+}
+
+impl Default for GitConfig {
+    fn default() -> Self {
+        Self {
+            remote: Self::default_remote(),
+            token_env: Self::default_token_env(),
+            push_branch: None,
+            push: Self::default_push(),
+            sign: SignConfig::default(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
 pub struct CommitConfig {
     #[serde(default)]
@@ -90,6 +333,228 @@ impl Default for CommitConfig {
     }
 }
 
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+pub struct VersionFileConfig {
+    pub path: PathBuf,
+
+    #[serde(default)]
+    pub pattern: Option<String>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+pub struct CargoConfig {
+    #[serde(default = "CargoConfig::default_manifest")]
+    pub manifest: PathBuf,
+
+    #[serde(default)]
+    pub cascade: bool,
+}
+
+impl CargoConfig {
+    #[inline]
+    fn default_manifest() -> PathBuf {
+        PathBuf::from("Cargo.toml")
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Default)]
+pub struct ScopeConfig {
+    #[serde(default)]
+    pub title: Option<String>,
+
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Default)]
+pub struct ExcludeConfig {
+    #[serde(default)]
+    pub scopes: Vec<String>,
+
+    #[serde(default)]
+    pub descriptions: Vec<String>,
+
+    #[serde(default)]
+    pub contributors: Vec<String>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum History {
+    #[default]
+    All,
+    FirstParent,
+    MergeTitles,
+}
+
+impl From<History> for HistoryStrategy {
+    fn from(history: History) -> Self {
+        match history {
+            History::All => HistoryStrategy::AllCommits,
+            History::FirstParent => HistoryStrategy::FirstParent,
+            History::MergeTitles => HistoryStrategy::MergeTitles,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Default)]
+pub struct ParsingConfig {
+    #[serde(default)]
+    pub type_aliases: HashMap<String, ChangeKind>,
+
+    #[serde(default)]
+    pub fallbacks: Vec<FallbackConfig>,
+
+    #[serde(default)]
+    pub ignore_scope_case: bool,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+pub struct FallbackConfig {
+    pub pattern: String,
+
+    #[serde(rename = "type")]
+    pub type_: ChangeKind,
+
+    #[serde(default)]
+    pub breaking: bool,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Feat,
+    Fix,
+}
+
+impl From<ChangeKind> for ChangeType<'static> {
+    fn from(kind: ChangeKind) -> Self {
+        match kind {
+            ChangeKind::Feat => ChangeType::Feature,
+            ChangeKind::Fix => ChangeType::Fix,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Default)]
+pub struct LintConfig {
+    #[serde(default)]
+    pub types: Vec<String>,
+
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Default)]
+pub struct NotesConfig {
+    #[serde(default)]
+    pub bodies: BodiesConfig,
+
+    #[serde(default)]
+    pub migration_guide: bool,
+
+    #[serde(default)]
+    pub contributors: bool,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum BodiesConfig {
+    #[default]
+    None,
+    All,
+    BreakingChanges,
+    Features,
+}
+
+impl From<BodiesConfig> for Bodies {
+    fn from(bodies: BodiesConfig) -> Self {
+        match bodies {
+            BodiesConfig::None => Bodies::Hidden,
+            BodiesConfig::All => Bodies::All,
+            BodiesConfig::BreakingChanges => Bodies::BreakingChangesOnly,
+            BodiesConfig::Features => Bodies::FeaturesOnly,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+pub struct BranchConfig {
+    /// Name of the branch, where `*` matches any sequence of characters (e.g. `release/*`)
+    pub name: String,
+
+    /// Greatest bump level allowed when releasing from this branch
+    #[serde(default)]
+    pub max_bump: Option<BumpLevel>,
+
+    /// Pre-release identifier of the versions released from this branch (e.g. `beta` for `2.0.0-beta.1`)
+    #[serde(default)]
+    pub prerelease: Option<String>,
+
+    /// Distribution channel of the releases, available as `{{channel}}` in the hooks (e.g. `next`)
+    #[serde(default)]
+    pub channel: Option<String>,
+}
+
+impl BranchConfig {
+    /// Whether the branch name matches the `name` pattern, where `*` matches any sequence of characters
+    pub fn matches(&self, branch: &str) -> bool {
+        let mut parts = self.name.split('*');
+        let mut rest = match parts.next().and_then(|prefix| branch.strip_prefix(prefix)) {
+            Some(rest) => rest,
+            None => return false,
+        };
+        let parts = parts.collect::<Vec<_>>();
+        match parts.split_last() {
+            None => rest.is_empty(),
+            Some((suffix, middle)) => {
+                for part in middle {
+                    match rest.find(part) {
+                        Some(index) => rest = &rest[index + part.len()..],
+                        None => return false,
+                    }
+                }
+                rest.ends_with(suffix)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Default)]
+pub struct VersioningConfig {
+    #[serde(default)]
+    pub pre_stable: PreStableConfig,
+
+    /// Token of the footer that makes the release stable (>= 1.0.0) when found in one of the released commits
+    #[serde(default)]
+    pub stabilize_footer: Option<String>,
+
+    /// Template of the build metadata appended to the released version (e.g. `git.{{sha}}`)
+    #[serde(default)]
+    pub build_metadata: Option<String>,
+
+    /// Whether the tag names include the build metadata
+    #[serde(default)]
+    pub tag_metadata: bool,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum PreStableConfig {
+    #[default]
+    InitialDevelopment,
+    Semver,
+}
+
+impl From<PreStableConfig> for PreStable {
+    fn from(policy: PreStableConfig) -> Self {
+        match policy {
+            PreStableConfig::InitialDevelopment => PreStable::InitialDevelopment,
+            PreStableConfig::Semver => PreStable::Semver,
+        }
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Deserialize, Default)]
 pub struct Hooks {
     #[serde(default)]
@@ -124,6 +589,25 @@ impl Display for Error {
 
 impl std::error::Error for Error {}
 
+/// Name that belongs to two scopes when the case is ignored
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AmbiguousScope {
+    name: String,
+    scopes: (String, String),
+}
+
+impl Display for AmbiguousScope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The scope name '{}' is ambiguous when ignoring the case (scopes '{}' and '{}')",
+            self.name, self.scopes.0, self.scopes.1
+        )
+    }
+}
+
+impl std::error::Error for AmbiguousScope {}
+
 #[derive(Debug)]
 enum Cause {
     CannotReadFile(io::Error),
@@ -138,6 +622,8 @@ impl From<io::Error> for Cause {
 
 #[cfg(test)]
 mod tests {
+    use rstest::rstest;
+
     use super::*;
 
     #[test]
@@ -303,4 +789,531 @@ mod tests {
             vec![PathBuf::from("CHANGELOG.md"), PathBuf::from("README.md")]
         )
     }
+
+    #[test]
+    fn no_scope_names_by_default() {
+        let config: Config = parse(r"a: b".as_bytes()).expect("Failed to parse config");
+
+        assert!(config.scopes.is_empty());
+        assert_eq!(config.scope_names(), ScopeNames::default());
+    }
+
+    #[test]
+    fn can_define_scope_aliases_and_titles() {
+        let config: Config = parse(
+            r"
+        scopes:
+            CLI:
+                title: Command line interface
+                aliases:
+                    - cli
+                    - command-line
+        "
+            .as_bytes(),
+        )
+        .expect("Failed to parse config");
+
+        let mut expected = ScopeNames::default();
+        expected.set_title("CLI", "Command line interface");
+        expected.add_alias("cli", "CLI");
+        expected.add_alias("command-line", "CLI");
+
+        assert_eq!(config.scope_names(), expected);
+    }
+
+    #[test]
+    fn excludes_nothing_by_default() {
+        let config: Config = parse(r"a: b".as_bytes()).expect("Failed to parse config");
+
+        assert_eq!(config.exclude, ExcludeConfig::default());
+    }
+
+    #[test]
+    fn can_define_exclusions() {
+        let config: Config = parse(
+            r#"
+        exclude:
+            scopes:
+                - ci
+                - internal
+            descriptions:
+                - "^bump "
+        "#
+            .as_bytes(),
+        )
+        .expect("Failed to parse config");
+
+        assert_eq!(
+            config.exclude,
+            ExcludeConfig {
+                scopes: vec![String::from("ci"), String::from("internal")],
+                descriptions: vec![String::from("^bump ")],
+                ..ExcludeConfig::default()
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_exclusion_pattern_is_rejected() {
+        let result = parse(
+            r#"
+        exclude:
+            descriptions:
+                - "("
+        "#
+            .as_bytes(),
+        );
+
+        assert!(matches!(result, Err(Cause::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn walks_all_commits_by_default() {
+        let config: Config = parse(r"a: b".as_bytes()).expect("Failed to parse config");
+
+        assert_eq!(
+            config.load_options().unwrap().strategy,
+            HistoryStrategy::AllCommits
+        );
+    }
+
+    #[rstest]
+    #[case("all", HistoryStrategy::AllCommits)]
+    #[case("first-parent", HistoryStrategy::FirstParent)]
+    #[case("merge-titles", HistoryStrategy::MergeTitles)]
+    fn can_define_history_strategy(#[case] value: &str, #[case] expected: HistoryStrategy) {
+        let config: Config =
+            parse(format!("history: {}", value).as_bytes()).expect("Failed to parse config");
+
+        assert_eq!(config.load_options().unwrap().strategy, expected);
+    }
+
+    #[test]
+    fn no_parsing_rules_by_default() {
+        let config: Config = parse(r"a: b".as_bytes()).expect("Failed to parse config");
+
+        assert_eq!(config.parsing, ParsingConfig::default());
+        assert_eq!(
+            config.load_options().unwrap().rules,
+            ParsingRules::default()
+        );
+    }
+
+    #[test]
+    fn can_define_parsing_rules() {
+        let config: Config = parse(
+            r#"
+        parsing:
+            type_aliases:
+                feature: feat
+                hotfix: fix
+            fallbacks:
+                - pattern: "^:sparkles:"
+                  type: feat
+                - pattern: "^:boom:"
+                  type: feat
+                  breaking: true
+            ignore_scope_case: true
+        "#
+            .as_bytes(),
+        )
+        .expect("Failed to parse config");
+
+        let mut expected = ParsingRules::default();
+        expected.add_type_alias("feature", ChangeType::Feature);
+        expected.add_type_alias("hotfix", ChangeType::Fix);
+        expected
+            .add_fallback("^:sparkles:", ChangeType::Feature, false)
+            .unwrap();
+        expected
+            .add_fallback("^:boom:", ChangeType::Feature, true)
+            .unwrap();
+
+        assert_eq!(config.load_options().unwrap().rules, expected);
+        assert!(config.parsing.ignore_scope_case);
+    }
+
+    #[rstest]
+    #[case("parsing: { type_aliases: { feature: chore } }")]
+    #[case("parsing: { fallbacks: [ { pattern: '(', type: feat } ] }")]
+    fn invalid_parsing_rules_are_rejected(#[case] config: &str) {
+        assert!(matches!(
+            parse(config.as_bytes()),
+            Err(Cause::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn no_lint_restriction_by_default() {
+        let config: Config = parse(r"a: b".as_bytes()).expect("Failed to parse config");
+
+        assert_eq!(config.lint, LintConfig::default());
+    }
+
+    #[test]
+    fn can_define_allowed_types_and_scopes() {
+        let config: Config = parse(
+            r"
+        lint:
+            types: [chore, docs]
+            scopes: [cli]
+        "
+            .as_bytes(),
+        )
+        .expect("Failed to parse config");
+
+        assert_eq!(
+            config.lint,
+            LintConfig {
+                types: vec![String::from("chore"), String::from("docs")],
+                scopes: vec![String::from("cli")],
+            }
+        );
+    }
+
+    #[test]
+    fn bodies_are_hidden_by_default() {
+        let config: Config = parse(r"a: b".as_bytes()).expect("Failed to parse config");
+
+        assert_eq!(config.notes.bodies, BodiesConfig::None);
+    }
+
+    #[rstest]
+    #[case("all", BodiesConfig::All)]
+    #[case("breaking-changes", BodiesConfig::BreakingChanges)]
+    #[case("features", BodiesConfig::Features)]
+    fn can_render_bodies(#[case] value: &str, #[case] expected: BodiesConfig) {
+        let config: Config = parse(format!("notes: {{ bodies: {} }}", value).as_bytes())
+            .expect("Failed to parse config");
+
+        assert_eq!(config.notes.bodies, expected);
+    }
+
+    #[test]
+    fn initial_development_by_default() {
+        let config: Config = parse(r"a: b".as_bytes()).expect("Failed to parse config");
+
+        assert_eq!(config.versioning, VersioningConfig::default());
+        assert_eq!(
+            config.versioning.pre_stable,
+            PreStableConfig::InitialDevelopment
+        );
+    }
+
+    #[test]
+    fn can_configure_versioning() {
+        let config: Config = parse(
+            r"
+versioning:
+  pre_stable: semver
+  stabilize_footer: Stable"
+                .as_bytes(),
+        )
+        .expect("Failed to parse config");
+
+        assert_eq!(
+            config.versioning,
+            VersioningConfig {
+                pre_stable: PreStableConfig::Semver,
+                stabilize_footer: Some(String::from("Stable")),
+                ..VersioningConfig::default()
+            }
+        );
+    }
+
+    #[rstest]
+    #[case("a: b", "1.2.3+git.abc1234", "v1.2.3")]
+    #[case("a: b", "1.2.3", "v1.2.3")]
+    #[case(
+        "versioning: { tag_metadata: true }",
+        "1.2.3+git.abc1234",
+        "v1.2.3+git.abc1234"
+    )]
+    #[case("tag_prefix: ''", "1.2.3-rc.1+build.5", "1.2.3-rc.1")]
+    fn tag_name(#[case] config: &str, #[case] version: &str, #[case] expected: &str) {
+        let config: Config = parse(config.as_bytes()).expect("Failed to parse config");
+
+        assert_eq!(config.tag_name(&version.parse().unwrap()), expected);
+    }
+
+    #[test]
+    fn push_to_origin_by_default() {
+        let config: Config = parse(r"a: b".as_bytes()).expect("Failed to parse config");
+
+        assert_eq!(config.git.remote, "origin");
+        assert_eq!(config.git.token_env, "GITHUB_TOKEN");
+        assert_eq!(config.git.push_branch, None);
+        assert!(config.git.push);
+    }
+
+    #[test]
+    fn can_configure_git_remote() {
+        let config: Config = parse(r"git: { remote: upstream, token_env: GIT_TOKEN }".as_bytes())
+            .expect("Failed to parse config");
+
+        assert_eq!(
+            config.git,
+            GitConfig {
+                remote: String::from("upstream"),
+                token_env: String::from("GIT_TOKEN"),
+                ..GitConfig::default()
+            }
+        );
+    }
+
+    #[test]
+    fn can_configure_signatures() {
+        let config: Config = parse(
+            r"
+git:
+  sign:
+    commits: true
+    tags: true
+    format: ssh
+    key: ~/.ssh/id_ed25519.pub"
+                .as_bytes(),
+        )
+        .expect("Failed to parse config");
+
+        assert_eq!(
+            config.git.sign,
+            SignConfig {
+                commits: Some(true),
+                tags: Some(true),
+                format: Some(SignFormat::Ssh),
+                key: Some(String::from("~/.ssh/id_ed25519.pub")),
+            }
+        );
+    }
+
+    #[test]
+    fn can_configure_push() {
+        let config: Config = parse(r"git: { push_branch: releases, push: false }".as_bytes())
+            .expect("Failed to parse config");
+
+        assert_eq!(config.git.push_branch.as_deref(), Some("releases"));
+        assert!(!config.git.push);
+    }
+
+    #[test]
+    fn can_configure_branches() {
+        let config: Config = parse(
+            r#"
+branches:
+  - name: main
+  - name: "release/*"
+    max_bump: patch
+  - name: next
+    prerelease: beta
+    channel: next"#
+                .as_bytes(),
+        )
+        .expect("Failed to parse config");
+
+        assert_eq!(
+            config.branches,
+            vec![
+                BranchConfig {
+                    name: String::from("main"),
+                    max_bump: None,
+                    prerelease: None,
+                    channel: None,
+                },
+                BranchConfig {
+                    name: String::from("release/*"),
+                    max_bump: Some(BumpLevel::Patch),
+                    prerelease: None,
+                    channel: None,
+                },
+                BranchConfig {
+                    name: String::from("next"),
+                    max_bump: None,
+                    prerelease: Some(String::from("beta")),
+                    channel: Some(String::from("next")),
+                },
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case("main", "main", true)]
+    #[case("main", "maintenance", false)]
+    #[case("release/*", "release/1.x", true)]
+    #[case("release/*", "release/", true)]
+    #[case("release/*", "feat/release/1.x", false)]
+    #[case("v*.x", "v1.x", true)]
+    #[case("v1.x", "v1-x", false)]
+    #[case("*", "main", true)]
+    #[case("*/*", "main", false)]
+    #[case("release-*.x", "release-.x", true)]
+    #[case("release-*.x", "release-1.2", false)]
+    #[case("a*b*b", "ab", false)]
+    #[case("a*b*b", "axbb", true)]
+    #[case("a*a", "a", false)]
+    #[case("[main]", "[main]", true)]
+    #[case("[main]", "m", false)]
+    fn branch_patterns(#[case] pattern: &str, #[case] branch: &str, #[case] expected: bool) {
+        let config = BranchConfig {
+            name: String::from(pattern),
+            max_bump: None,
+            prerelease: None,
+            channel: None,
+        };
+
+        assert_eq!(config.matches(branch), expected);
+    }
+
+    #[test]
+    fn can_enable_migration_guide() {
+        let config: Config =
+            parse(r"notes: { migration_guide: true }".as_bytes()).expect("Failed to parse config");
+
+        assert!(config.notes.migration_guide);
+    }
+
+    #[test]
+    fn can_credit_contributors() {
+        let config: Config = parse(
+            r"
+        notes:
+            contributors: true
+        exclude:
+            contributors: ['\[bot\]']
+        "
+            .as_bytes(),
+        )
+        .expect("Failed to parse config");
+
+        assert!(config.notes.contributors);
+        assert_eq!(config.exclude.contributors, vec![String::from(r"\[bot\]")]);
+    }
+
+    #[test]
+    fn invalid_contributor_pattern_is_rejected() {
+        assert!(matches!(
+            parse(r"exclude: { contributors: ['('] }".as_bytes()),
+            Err(Cause::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn can_define_version_files() {
+        let config: Config = parse(
+            r#"
+        version_files:
+            - path: Cargo.toml
+            - path: src/version.txt
+              pattern: 'VERSION = "(.*)"'
+        "#
+            .as_bytes(),
+        )
+        .expect("Failed to parse config");
+
+        assert_eq!(
+            config.version_files,
+            vec![
+                VersionFileConfig {
+                    path: PathBuf::from("Cargo.toml"),
+                    pattern: None,
+                },
+                VersionFileConfig {
+                    path: PathBuf::from("src/version.txt"),
+                    pattern: Some(String::from(r#"VERSION = "(.*)""#)),
+                },
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case("version_files: [ { path: VERSION } ]")]
+    #[case("version_files: [ { path: VERSION, pattern: '(' } ]")]
+    fn invalid_version_files_are_rejected(#[case] config: &str) {
+        assert!(matches!(
+            parse(config.as_bytes()),
+            Err(Cause::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn no_cargo_workspace_by_default() {
+        let config: Config = parse(r"a: b".as_bytes()).expect("Failed to parse config");
+
+        assert_eq!(config.cargo, None);
+    }
+
+    #[rstest]
+    #[case("cargo: {}", "Cargo.toml", false)]
+    #[case(
+        "cargo: { manifest: rust/Cargo.toml, cascade: true }",
+        "rust/Cargo.toml",
+        true
+    )]
+    fn can_release_cargo_workspace(
+        #[case] config: &str,
+        #[case] expected_manifest: &str,
+        #[case] expected_cascade: bool,
+    ) {
+        let config: Config = parse(config.as_bytes()).expect("Failed to parse config");
+
+        assert_eq!(
+            config.cargo,
+            Some(CargoConfig {
+                manifest: PathBuf::from(expected_manifest),
+                cascade: expected_cascade,
+            })
+        );
+    }
+
+    #[rstest]
+    #[case("{ cargo: {}, version_files: [{ path: Cargo.toml }] }", false)]
+    #[case("{ cargo: {}, version_files: [{ path: rust/Cargo.toml }] }", false)]
+    #[case("{ cargo: {}, version_files: [{ path: package.json }] }", true)]
+    #[case("{ version_files: [{ path: Cargo.toml }] }", true)]
+    fn cargo_manifests_have_a_single_writer(#[case] config: &str, #[case] expected_valid: bool) {
+        assert_eq!(parse(config.as_bytes()).is_ok(), expected_valid);
+    }
+
+    #[rstest]
+    #[case("{ CLI: {}, cli: {} }", true, false)]
+    #[case("{ CLI: {}, cli: {} }", false, true)]
+    #[case(
+        "{ cli: { aliases: [command] }, lib: { aliases: [Command] } }",
+        true,
+        false
+    )]
+    #[case("{ cli: { aliases: [CLI, Cli] }, lib: {} }", true, true)]
+    fn scope_names_are_unambiguous_when_ignoring_case(
+        #[case] scopes: &str,
+        #[case] ignore_case: bool,
+        #[case] expected_valid: bool,
+    ) {
+        let config = format!(
+            "{{ scopes: {}, parsing: {{ ignore_scope_case: {} }} }}",
+            scopes, ignore_case
+        );
+
+        assert_eq!(parse(config.as_bytes()).is_ok(), expected_valid);
+    }
+
+    #[test]
+    fn can_define_baseline_file() {
+        let config: Config =
+            parse(r"baseline: { path: Cargo.toml }".as_bytes()).expect("Failed to parse config");
+
+        assert_eq!(
+            config.baseline,
+            Some(VersionFileConfig {
+                path: PathBuf::from("Cargo.toml"),
+                pattern: None,
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_baseline_file_is_rejected() {
+        assert!(matches!(
+            parse(r"baseline: { path: VERSION }".as_bytes()),
+            Err(Cause::InvalidConfig(_))
+        ));
+    }
 }
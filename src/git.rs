@@ -1,19 +1,26 @@
-use std::fmt::{Display, Formatter};
+use std::env;
+use std::fmt::{self, Display, Formatter};
 use std::path::PathBuf;
-use std::process::{Command, ExitStatus};
 use std::str::FromStr;
-use std::{fmt, io};
 
-use git2::{ObjectType, Oid, Repository, Signature};
+use git2::{
+    AutotagOption, Cred, CredentialType, FetchOptions, ObjectType, Oid, PushOptions,
+    RemoteCallbacks, Repository, Signature, StatusOptions,
+};
 
-use crate::config::CommitConfig;
+use autorel_chlg::git::RELEASE_TRAILER;
+
+use crate::config::{CommitConfig, GitConfig};
+use crate::sign::{self, Signer};
+use crate::version_file::VersionFile;
 
 #[derive(Debug)]
 pub enum Error {
     LibGitErr(git2::Error),
-    IoError(io::Error),
-    StatusCode(ExitStatus),
+    SignError(sign::Error),
     RepositoryDirtyAfterCommit,
+    DetachedHead,
+    NotUtf8(&'static str),
 }
 
 impl From<git2::Error> for Error {
@@ -22,15 +29,9 @@ impl From<git2::Error> for Error {
     }
 }
 
-impl From<io::Error> for Error {
-    fn from(err: io::Error) -> Self {
-        Self::IoError(err)
-    }
-}
-
-impl From<ExitStatus> for Error {
-    fn from(err: ExitStatus) -> Self {
-        Self::StatusCode(err)
+impl From<sign::Error> for Error {
+    fn from(err: sign::Error) -> Self {
+        Self::SignError(err)
     }
 }
 
@@ -38,58 +39,135 @@ impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             Error::LibGitErr(err) => err.fmt(f),
-            Error::IoError(err) => write!(f, "Failed to invoke git: {}", err),
-            Error::StatusCode(status) => {
-                write!(f, "Git command failed.")?;
-                if let Some(code) = status.code() {
-                    write!(f, " Status code: {}", code)
-                } else {
-                    Ok(())
-                }
-            }
+            Error::SignError(err) => err.fmt(f),
             Error::RepositoryDirtyAfterCommit => {
                 writeln!(f, "Git repository is dirty!")
             }
+            Error::DetachedHead => write!(f, "Cannot push from a detached HEAD"),
+            Error::NotUtf8(what) => write!(f, "Cannot sign the {}: it isn't valid UTF-8", what),
         }
     }
 }
 
 impl std::error::Error for Error {}
 
-pub fn check_is_clean(_: &Repository) -> Result<(), Error> {
+pub fn check_is_clean(repo: &Repository) -> Result<(), Error> {
     println!("> check if repository is clean");
-    let output = Command::new("git")
-        .arg("status")
-        .arg("--porcelain")
-        .output()?;
-
-    if !output.status.success() {
-        Err(Error::StatusCode(output.status))
-    } else if output.stdout.is_empty() {
+    if changed_files(repo)?.is_empty() {
         Ok(())
     } else {
         Err(Error::RepositoryDirtyAfterCommit)
     }
 }
 
+/// Returns the paths of the files that are modified or untracked (and not ignored)
+pub fn changed_files(repo: &Repository) -> Result<Vec<String>, Error> {
+    let mut options = StatusOptions::new();
+    options.include_untracked(true).include_ignored(false);
+    Ok(repo
+        .statuses(Some(&mut options))?
+        .iter()
+        .filter_map(|entry| entry.path().map(String::from))
+        .collect())
+}
+
+/// Returns the greatest version of the release tags reachable from `HEAD`
+///
+/// The tags of other branches (e.g. of the next major version, when releasing from a maintenance branch) are ignored.
 pub fn find_latest_release<V: FromStr + Ord>(
     repo: &Repository,
     tag_prefix: &str,
 ) -> Result<Option<V>, Error> {
-    Ok(repo
+    let head = repo.head()?.peel_to_commit()?.id();
+    let mut latest = None;
+    for (version, commit) in release_tags(repo, tag_prefix)? {
+        let reachable = commit == head || repo.graph_descendant_of(head, commit)?;
+        if reachable {
+            latest = latest.max(Some(version));
+        }
+    }
+    Ok(latest)
+}
+
+/// Returns the versions of all the release tags, whether they are reachable from `HEAD` or not
+pub fn find_releases<V: FromStr>(repo: &Repository, tag_prefix: &str) -> Result<Vec<V>, Error> {
+    Ok(release_tags(repo, tag_prefix)?
+        .into_iter()
+        .map(|(version, _)| version)
+        .collect())
+}
+
+fn release_tags<V: FromStr>(repo: &Repository, tag_prefix: &str) -> Result<Vec<(V, Oid)>, Error> {
+    let mut tags = Vec::new();
+    for name in repo
         .tag_names(Some(&(String::from(tag_prefix) + "*")))?
         .iter()
-        .filter_map(|tag| {
-            tag.and_then(|it| it.strip_prefix(tag_prefix))
-                .and_then(|it| it.parse().ok())
-        })
-        .max())
+        .flatten()
+    {
+        let version = match name.strip_prefix(tag_prefix).and_then(|it| it.parse().ok()) {
+            Some(version) => version,
+            None => continue,
+        };
+        // Tags of other objects (e.g. a tree or a blob) aren't releases
+        let commit = match repo
+            .revparse_single(&format!("refs/tags/{}", name))
+            .and_then(|it| it.peel_to_commit())
+        {
+            Ok(commit) => commit,
+            Err(_) => continue,
+        };
+        tags.push((version, commit.id()));
+    }
+    Ok(tags)
+}
+
+/// Returns the name of the checked out branch (`None` if the `HEAD` is detached)
+pub fn current_branch(repo: &Repository) -> Result<Option<String>, Error> {
+    let head = repo.head()?;
+    Ok(if head.is_branch() {
+        head.shorthand().map(String::from)
+    } else {
+        None
+    })
+}
+
+/// Finds the commit that wrote `version` in the file, following the first parents from `HEAD`
+///
+/// Returns `None` if the version isn't written in the file at `HEAD`.
+pub fn find_version_commit(
+    repo: &Repository,
+    file: &VersionFile,
+    version: &str,
+) -> Result<Option<Oid>, Error> {
+    let mut walker = repo.revwalk()?;
+    walker.push_head()?;
+    walker.simplify_first_parent()?;
+
+    let mut found = None;
+    for oid in walker {
+        let commit = repo.find_commit(oid?)?;
+        let entry = match commit.tree()?.get_path(file.path()) {
+            Ok(entry) => entry,
+            Err(_) => break,
+        };
+        let blob = entry.to_object(repo)?.peel_to_blob()?;
+        let content = String::from_utf8_lossy(blob.content());
+
+        if file.read_version(&content).as_deref() == Some(version) {
+            found = Some(commit.id());
+        } else {
+            break;
+        }
+    }
+
+    Ok(found)
 }
 
 pub fn commit(
     repo: &Repository,
     config: &CommitConfig,
-    tag_prefix: &str,
+    git_config: &GitConfig,
+    tag_name: &str,
     version_str: &str,
     dry_run: bool,
 ) -> Result<(), Error> {
@@ -98,11 +176,16 @@ pub fn commit(
         Err(_) => Signature::now("autorel", "autorel")?,
     };
 
+    let repo_config = repo.config()?;
+    let commit_signer = Signer::for_commits(&git_config.sign, &repo_config)?;
+    let tag_signer = Signer::for_tags(&git_config.sign, &repo_config)?;
+
     let tree = stage_files(repo, &config.files, dry_run)?;
 
     let commit = perform_commit(
         repo,
         &signature,
+        commit_signer.as_ref(),
         tree,
         &config.message,
         version_str,
@@ -111,56 +194,203 @@ pub fn commit(
 
     check_is_clean(repo)?;
 
-    tag(repo, &signature, commit, tag_prefix, version_str, dry_run)?;
+    tag(
+        repo,
+        &signature,
+        tag_signer.as_ref(),
+        commit,
+        tag_name,
+        version_str,
+        dry_run,
+    )?;
 
-    push(repo, dry_run)
+    push(repo, git_config, tag_name, dry_run)
 }
 
 pub fn tag(
     repo: &Repository,
     signature: &Signature<'_>,
+    signer: Option<&Signer>,
     last_commit_id: Oid,
-    prefix: &str,
+    tag_name: &str,
     version: &str,
     dry_run: bool,
 ) -> Result<(), Error> {
-    let tag_name = format!("{}{}", prefix, version);
-    println!("> git tag {}", tag_name);
-
+    let message = format!("Release {}", version);
     let object = repo.find_object(last_commit_id, Some(ObjectType::Commit))?;
 
-    if !dry_run {
-        repo.tag(
-            &tag_name,
-            &object,
-            signature,
-            &format!("Release {}", version),
-            false,
-        )
-        .map(|_| ())
-        .map_err(Error::from)
-    } else {
-        Ok(())
+    match signer {
+        None => {
+            println!("> git tag {}", tag_name);
+            if !dry_run {
+                repo.tag(tag_name, &object, signature, &message, false)?;
+            }
+        }
+        Some(signer) => {
+            println!("> git tag -s {}", tag_name);
+            if !dry_run {
+                let content = format!(
+                    "object {}\ntype commit\ntag {}\ntagger {}\n\n{}\n",
+                    last_commit_id,
+                    tag_name,
+                    format_signature(signature)?,
+                    message
+                );
+                let signed = content.clone() + &signer.sign(&content)?;
+                let tag_id = repo.odb()?.write(ObjectType::Tag, signed.as_bytes())?;
+                repo.reference(
+                    &format!("refs/tags/{}", tag_name),
+                    tag_id,
+                    false,
+                    &format!("tag: {}", tag_name),
+                )?;
+            }
+        }
     }
+
+    Ok(())
 }
 
-pub fn push(_: &Repository, dry_run: bool) -> Result<(), Error> {
-    println!("> git push");
+/// Formats the signature as in the git objects (`Name <email> timestamp offset`)
+///
+/// Fails if the name or the email isn't valid UTF-8, rather than writing an invalid tagger.
+fn format_signature(signature: &Signature<'_>) -> Result<String, Error> {
+    let when = signature.when();
+    let offset = when.offset_minutes().abs();
+    Ok(format!(
+        "{} <{}> {} {}{:02}{:02}",
+        signature.name().ok_or(Error::NotUtf8("tagger name"))?,
+        signature.email().ok_or(Error::NotUtf8("tagger email"))?,
+        when.seconds(),
+        if when.offset_minutes() < 0 { '-' } else { '+' },
+        offset / 60,
+        offset % 60
+    ))
+}
+
+/// Pushes the current branch (to the configured push branch, if any) and the tag to the configured remote
+pub fn push(
+    repo: &Repository,
+    config: &GitConfig,
+    tag_name: &str,
+    dry_run: bool,
+) -> Result<(), Error> {
+    if !config.push {
+        return Ok(());
+    }
+
+    let branch = current_branch(repo)?.ok_or(Error::DetachedHead)?;
+    let target = config.push_branch.as_deref().unwrap_or(&branch);
+    if target == branch {
+        println!("> git push {} {} {}", config.remote, branch, tag_name);
+    } else {
+        println!(
+            "> git push {} {}:{} {}",
+            config.remote, branch, target, tag_name
+        );
+    }
 
     if !dry_run {
-        let status = Command::new("git")
-            .arg("push")
-            .arg("--follow-tags")
-            .status()?;
+        let mut callbacks = remote_callbacks(repo, config)?;
+        callbacks.push_update_reference(|reference, status| match status {
+            None => Ok(()),
+            Some(status) => Err(git2::Error::from_str(&format!(
+                "The push of '{}' was rejected: {}",
+                reference, status
+            ))),
+        });
+        let mut options = PushOptions::new();
+        options.remote_callbacks(callbacks);
 
-        if !status.success() {
-            return Err(Error::from(status));
-        }
+        repo.find_remote(&config.remote)?.push(
+            &[
+                format!("refs/heads/{}:refs/heads/{}", branch, target),
+                format!("refs/tags/{0}:refs/tags/{0}", tag_name),
+            ],
+            Some(&mut options),
+        )?;
     }
 
     Ok(())
 }
 
+/// Returns whether the tag exists on the configured remote
+///
+/// The tag is fetched to a temporary reference, because listing the references of an empty remote isn't supported.
+pub fn remote_tag_exists(
+    repo: &Repository,
+    config: &GitConfig,
+    tag_name: &str,
+) -> Result<bool, Error> {
+    let probe = format!("refs/autorel/remote-tags/{}", tag_name);
+    let mut options = FetchOptions::new();
+    options
+        .remote_callbacks(remote_callbacks(repo, config)?)
+        .download_tags(AutotagOption::None)
+        .update_fetchhead(false);
+
+    repo.find_remote(&config.remote)?.fetch(
+        &[format!("+refs/tags/{}:{}", tag_name, probe)],
+        Some(&mut options),
+        None,
+    )?;
+
+    match repo.find_reference(&probe) {
+        Ok(mut reference) => {
+            reference.delete()?;
+            Ok(true)
+        }
+        Err(_) => Ok(false),
+    }
+}
+
+/// Callbacks authenticating to the remote, trying in order:
+/// the SSH agent, the token of the configured environment variable, the git credential helper, and the default credentials
+fn remote_callbacks<'a>(
+    repo: &Repository,
+    config: &'a GitConfig,
+) -> Result<RemoteCallbacks<'a>, Error> {
+    let git_config = repo.config()?;
+    let mut tried_agent = false;
+    let mut tried_token = false;
+    let mut tried_helper = false;
+    let mut tried_default = false;
+
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(move |url, username, allowed| {
+        if allowed.contains(CredentialType::SSH_KEY) && !tried_agent {
+            tried_agent = true;
+            return Cred::ssh_key_from_agent(username.unwrap_or("git"));
+        }
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            if !tried_token {
+                tried_token = true;
+                if let Ok(token) = env::var(&config.token_env) {
+                    return Cred::userpass_plaintext(
+                        username.unwrap_or("x-access-token"),
+                        &token,
+                    );
+                }
+            }
+            if !tried_helper {
+                tried_helper = true;
+                if let Ok(cred) = Cred::credential_helper(&git_config, url, username) {
+                    return Ok(cred);
+                }
+            }
+        }
+        if allowed.contains(CredentialType::DEFAULT) && !tried_default {
+            tried_default = true;
+            return Cred::default();
+        }
+        Err(git2::Error::from_str(&format!(
+            "No valid credentials for '{}' (tried the SSH agent, the {} environment variable and the git credential helper)",
+            url, config.token_env
+        )))
+    });
+    Ok(callbacks)
+}
+
 fn stage_files(repo: &Repository, files: &[PathBuf], dry_run: bool) -> Result<Oid, git2::Error> {
     let mut index = repo.index()?;
     for file in files {
@@ -181,30 +411,183 @@ fn stage_files(repo: &Repository, files: &[PathBuf], dry_run: bool) -> Result<Oi
 fn perform_commit(
     repo: &Repository,
     signature: &Signature<'_>,
+    signer: Option<&Signer>,
     tree_id: Oid,
     commit_message: &str,
     version_str: &str,
     dry_run: bool,
-) -> Result<Oid, git2::Error> {
-    let commit_message = commit_message.replace("{{version}}", version_str);
-    println!("> git commit -m \"{}\"", commit_message);
+) -> Result<Oid, Error> {
+    let commit_message = format!(
+        "{}\n\n{}",
+        commit_message.replace("{{version}}", version_str),
+        RELEASE_TRAILER
+    );
+    let sign_flag = if signer.is_some() { "-S " } else { "" };
+    println!("> git commit {}-m \"{}\"", sign_flag, commit_message);
 
     let mut walker = repo.revwalk()?;
     walker.push_head()?;
     let last_commit_id = walker.next().expect("No previous commit found")?;
     let last_commit = repo.find_commit(last_commit_id)?;
 
-    if !dry_run {
-        let tree = repo.find_tree(tree_id)?;
-        repo.commit(
+    if dry_run {
+        return Ok(last_commit_id);
+    }
+
+    let tree = repo.find_tree(tree_id)?;
+    match signer {
+        None => Ok(repo.commit(
             Some("HEAD"),
             signature,
             signature,
             &commit_message,
             &tree,
             &[&last_commit],
+        )?),
+        Some(signer) => {
+            let buffer = repo.commit_create_buffer(
+                signature,
+                signature,
+                &commit_message,
+                &tree,
+                &[&last_commit],
+            )?;
+            let content = buffer.as_str().ok_or(Error::NotUtf8("commit"))?;
+            let commit_id = repo.commit_signed(content, &signer.sign(content)?, Some("gpgsig"))?;
+            repo.find_reference("HEAD")?
+                .resolve()?
+                .set_target(commit_id, &format!("commit: {}", commit_message))?;
+            Ok(commit_id)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use git2::Time;
+    use semver::Version;
+    use tempfile::TempDir;
+
+    use crate::sign::SignFormat;
+    use crate::test_repo::TestRepo;
+
+    use super::*;
+
+    #[test]
+    fn latest_release_ignores_tags_not_pointing_to_a_commit() {
+        let test = TestRepo::new();
+        let first = test.commit_files("feat: first", &[("README.md", "first")]);
+        test.tag("v1.0.0", first);
+        let tree = test.repo.find_commit(first).unwrap().tree_id();
+        test.tag("v2.0.0", tree);
+
+        let latest: Option<Version> =
+            find_latest_release(&test.repo, "v").expect("Failed to find the latest release");
+
+        assert_eq!(latest, Some(Version::new(1, 0, 0)));
+    }
+
+    const FAKE_SIGNATURE: &str =
+        "-----BEGIN PGP SIGNATURE-----\nc2lnbmF0dXJl\n-----END PGP SIGNATURE-----\n";
+
+    /// Program in a temporary directory, that signs like `gpg` with a fixed signature
+    #[cfg(unix)]
+    fn fake_gpg() -> (TempDir, String) {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let program = dir.path().join("fake-gpg");
+        fs::write(
+            &program,
+            format!(
+                "#!/bin/sh\ncat > /dev/null\nprintf '%s' '{}'\n",
+                FAKE_SIGNATURE
+            ),
         )
-    } else {
-        Ok(last_commit_id)
+        .unwrap();
+        fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+        let program = program.to_str().unwrap().to_owned();
+        (dir, program)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_signed_commit() {
+        let test = TestRepo::new();
+        let parent = test.commit_files("feat: first", &[("README.md", "first")]);
+        let (_dir, program) = fake_gpg();
+        let mut repo_config = test.repo.config().unwrap();
+        repo_config.set_bool("commit.gpgsign", true).unwrap();
+        repo_config.set_bool("tag.gpgsign", false).unwrap();
+        repo_config.set_str("gpg.format", "openpgp").unwrap();
+        repo_config.set_str("gpg.program", &program).unwrap();
+        let git_config = GitConfig {
+            push: false,
+            ..GitConfig::default()
+        };
+
+        commit(
+            &test.repo,
+            &CommitConfig::default(),
+            &git_config,
+            "v1.0.0",
+            "1.0.0",
+            false,
+        )
+        .expect("Failed to commit");
+
+        let head = test.repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.parent_id(0).unwrap(), parent);
+        let (signature, signed) = test
+            .repo
+            .extract_signature(&head.id(), Some("gpgsig"))
+            .expect("The commit isn't signed");
+        assert_eq!(signature.as_str(), Some(FAKE_SIGNATURE));
+        assert!(signed
+            .as_str()
+            .unwrap()
+            .ends_with(&format!("\n\nchore: release 1.0.0\n\n{}", RELEASE_TRAILER)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_signed_tag() {
+        let test = TestRepo::new();
+        let commit = test.commit_files("feat: first", &[("README.md", "first")]);
+        let (_dir, program) = fake_gpg();
+        let signer = Signer {
+            format: SignFormat::Openpgp,
+            key: None,
+            program,
+        };
+        let signature =
+            Signature::new("Test", "test@example.com", &Time::new(1_600_000_000, -90)).unwrap();
+
+        tag(
+            &test.repo,
+            &signature,
+            Some(&signer),
+            commit,
+            "v1.0.0",
+            "1.0.0",
+            false,
+        )
+        .expect("Failed to tag");
+
+        let tag_id = test.repo.refname_to_id("refs/tags/v1.0.0").unwrap();
+        let tag = test.repo.find_tag(tag_id).expect("Invalid tag object");
+        assert_eq!(tag.name(), Some("v1.0.0"));
+        assert_eq!(tag.target_id(), commit);
+        let tagger = tag.tagger().unwrap();
+        assert_eq!(tagger.name(), Some("Test"));
+        assert_eq!(tagger.email(), Some("test@example.com"));
+        assert_eq!(tagger.when().seconds(), 1_600_000_000);
+        assert_eq!(tagger.when().offset_minutes(), -90);
+        assert_eq!(
+            tag.message(),
+            Some(format!("Release 1.0.0\n{}", FAKE_SIGNATURE).as_str())
+        );
     }
 }
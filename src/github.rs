@@ -22,7 +22,7 @@ lazy_static! {
 
 pub fn create_github_release(
     config: &GithubConfig,
-    tag_prefix: &str,
+    tag_name: &str,
     version_str: String,
     changelog: &ChangeLog,
     dry_run: bool,
@@ -32,7 +32,7 @@ pub fn create_github_release(
 
     println!("> Create release {}", version_str);
     let upload_url = if !dry_run {
-        client.create_release(tag_prefix, version_str, changelog.markdown().to_string())?
+        client.create_release(tag_name, version_str, changelog.markdown().to_string())?
     } else {
         String::default()
     };
@@ -47,9 +47,46 @@ pub fn create_github_release(
     Ok(())
 }
 
+/// Sets the github handle of the contributors who authored a commit, as known by github
+///
+/// This is best-effort: nothing is resolved if the `GITHUB_TOKEN` env. variable is absent,
+/// and the contributors are credited by their name if github cannot be queried.
+pub fn resolve_handles(config: &GithubConfig, changelog: &mut ChangeLog) {
+    let token = match std::env::var("GITHUB_TOKEN") {
+        Ok(token) => token,
+        Err(_) => return,
+    };
+    if let Err(err) = try_resolve_handles(config, &token, changelog) {
+        eprintln!(
+            "Cannot resolve the github handles of the contributors ({}), they are credited by name",
+            err
+        );
+    }
+}
+
+fn try_resolve_handles(
+    config: &GithubConfig,
+    token: &str,
+    changelog: &mut ChangeLog,
+) -> Result<(), Error> {
+    let client = Client::new(&config.repo, token)?;
+
+    for contributor in changelog.contributors_mut() {
+        if contributor.handle.is_some() {
+            continue;
+        }
+        if let Some(commit) = &contributor.commit {
+            contributor.handle = client.commit_author(commit)?;
+        }
+    }
+
+    Ok(())
+}
+
 struct Client {
     client: reqwest::blocking::Client,
     release_endpoint: String,
+    commits_endpoint: String,
 }
 
 impl Client {
@@ -79,17 +116,18 @@ impl Client {
         Ok(Self {
             client,
             release_endpoint: format!("https://api.github.com/repos/{}/releases", repo),
+            commits_endpoint: format!("https://api.github.com/repos/{}/commits", repo),
         })
     }
 
     fn create_release(
         &self,
-        tag_prefix: &str,
+        tag_name: &str,
         version_str: String,
         body: String,
     ) -> Result<String, Error> {
         let mut data: HashMap<&str, String> = HashMap::with_capacity(3);
-        data.insert("tag_name", format!("{}{}", tag_prefix, version_str));
+        data.insert("tag_name", String::from(tag_name));
         data.insert("name", version_str);
         data.insert("body", body);
 
@@ -109,6 +147,27 @@ impl Client {
         Ok(payload.upload_url)
     }
 
+    /// Returns the login of the github user who authored the commit, if github knows them
+    fn commit_author(&self, sha: &str) -> Result<Option<String>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/{}", self.commits_endpoint, sha))
+            .send()?;
+
+        if response.status() == StatusCode::NOT_FOUND
+            || response.status() == StatusCode::UNPROCESSABLE_ENTITY
+        {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(Error::ApiError(response.status()));
+        }
+
+        let payload: CommitPayload = response.json()?;
+
+        Ok(payload.author.map(|author| author.login))
+    }
+
     fn upload_file(&self, url: &str, file: &Path) -> Result<(), Box<dyn std::error::Error>> {
         let file_name = file.display().to_string();
         let parameters = form_urlencoded::Serializer::new(String::new())
@@ -116,7 +175,7 @@ impl Client {
             .append_pair("label", &file_name)
             .finish();
 
-        let url = format!("{}?{}", url.splitn(2, '{').next().unwrap(), parameters);
+        let url = format!("{}?{}", url.split('{').next().unwrap(), parameters);
 
         let response = self
             .client
@@ -139,6 +198,16 @@ struct ReleasePayload {
     upload_url: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct CommitPayload {
+    author: Option<UserPayload>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserPayload {
+    login: String,
+}
+
 #[derive(Debug)]
 pub enum Error {
     CannotReachApi(reqwest::Error),
@@ -0,0 +1,328 @@
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+use git2::Repository;
+
+use autorel_chlg::{ChangeType, ParseError, ParsingRules, ScopeNames};
+
+use crate::cli::LintOpts;
+use crate::config::{self, Config, LintConfig};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Violation {
+    NotConventional(ParseError),
+    UnknownType { type_: String, allowed: Vec<String> },
+    UnknownScope { scope: String, allowed: Vec<String> },
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::NotConventional(err) => err.fmt(f),
+            Violation::UnknownType { type_, allowed } => write!(
+                f,
+                "Unknown type `{}` (allowed types: {})",
+                type_,
+                allowed.join(", ")
+            ),
+            Violation::UnknownScope { scope, allowed } => write!(
+                f,
+                "Unknown scope `{}` (allowed scopes: {})",
+                scope,
+                allowed.join(", ")
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    CannotReadMessage(io::Error),
+    InvalidMessage(Violation),
+    InvalidCommits(usize),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::CannotReadMessage(err) => write!(f, "Cannot read the commit message: {}", err),
+            Error::InvalidMessage(violation) => violation.fmt(f),
+            Error::InvalidCommits(1) => write!(
+                f,
+                "1 commit doesn't follow the conventional commits convention"
+            ),
+            Error::InvalidCommits(count) => write!(
+                f,
+                "{} commits don't follow the conventional commits convention",
+                count
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::CannotReadMessage(err)
+    }
+}
+
+/// Rules of the configuration that the commit messages must follow
+///
+/// The types and scopes are resolved like when releasing (type aliases, fallback patterns and scope aliases),
+/// so that a message is valid if and only if the release understands it.
+pub struct Linter {
+    rules: ParsingRules,
+    scope_names: ScopeNames,
+    config: LintConfig,
+}
+
+impl Linter {
+    pub fn new(config: &Config) -> Result<Self, regex::Error> {
+        Ok(Self {
+            rules: config.load_options()?.rules,
+            scope_names: config.scope_names(),
+            config: config.lint.clone(),
+        })
+    }
+
+    /// Verifies that the message is a conventional commit (or matches a fallback), with a type and scope allowed by the configuration
+    ///
+    /// `feat` and `fix` are always allowed. Any type or scope is allowed if the respective list is empty.
+    pub fn check(&self, message: &str) -> Result<(), Violation> {
+        let change = self
+            .rules
+            .try_parse(message)
+            .map_err(Violation::NotConventional)?;
+
+        if let ChangeType::Custom(type_) = change.type_ {
+            if !self.config.types.is_empty()
+                && !self
+                    .config
+                    .types
+                    .iter()
+                    .any(|it| it.eq_ignore_ascii_case(type_))
+            {
+                let mut allowed = vec![String::from("feat"), String::from("fix")];
+                allowed.extend(self.config.types.iter().cloned());
+                return Err(Violation::UnknownType {
+                    type_: type_.to_owned(),
+                    allowed,
+                });
+            }
+        }
+
+        if let Some(scope) = change.scope {
+            let scope = self.scope_names.resolve(scope);
+            if !self.config.scopes.is_empty()
+                && !self
+                    .config
+                    .scopes
+                    .iter()
+                    .any(|it| self.scope_names.resolve(it) == scope)
+            {
+                return Err(Violation::UnknownScope {
+                    scope,
+                    allowed: self.config.scopes.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub fn run(config_path: &Path, options: &LintOpts) -> Result<(), Box<dyn std::error::Error>> {
+    let linter = Linter::new(&config::read(config_path)?)?;
+
+    match &options.range {
+        Some(range) => check_range(&Repository::open(".")?, range, &linter),
+        None => {
+            let message = match &options.file {
+                Some(file) => fs::read_to_string(file).map_err(Error::from)?,
+                None => {
+                    let mut message = String::new();
+                    io::stdin()
+                        .read_to_string(&mut message)
+                        .map_err(Error::from)?;
+                    message
+                }
+            };
+            linter
+                .check(&strip_comments(&message))
+                .map_err(Error::InvalidMessage)?;
+            println!("Valid commit message");
+            Ok(())
+        }
+    }
+}
+
+fn check_range(
+    repo: &Repository,
+    range: &str,
+    linter: &Linter,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut walker = repo.revwalk()?;
+    walker.push_range(range)?;
+
+    let mut invalid_count = 0;
+    for oid in walker {
+        let commit = repo.find_commit(oid?)?;
+        if commit.parent_count() > 1 {
+            continue;
+        }
+
+        if let Err(violation) = linter.check(commit.message().unwrap_or_default()) {
+            invalid_count += 1;
+            println!(
+                "{:.7} {}\n  {}",
+                commit.id(),
+                commit.summary().unwrap_or_default(),
+                violation
+            );
+        }
+    }
+
+    if invalid_count > 0 {
+        Err(Box::new(Error::InvalidCommits(invalid_count)))
+    } else {
+        println!("All commits are valid");
+        Ok(())
+    }
+}
+
+/// Line of `git commit -v` above the diff, that is not part of the message
+const SCISSORS: &str = "# ------------------------ >8 ------------------------";
+
+/// Removes the comment lines git adds to the message file of the `commit-msg` hook,
+/// and everything below the scissors line
+fn strip_comments(message: &str) -> String {
+    message
+        .lines()
+        .take_while(|line| *line != SCISSORS)
+        .filter(|line| !line.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use autorel_chlg::ParseErrorKind;
+
+    use super::*;
+
+    fn linter(config: LintConfig) -> Linter {
+        Linter {
+            rules: ParsingRules::default(),
+            scope_names: ScopeNames::default(),
+            config,
+        }
+    }
+
+    #[rstest]
+    #[case("feat: Hello world")]
+    #[case("fix(scope)!: Hello world")]
+    #[case("chore: Hello world\n\nwith a body")]
+    fn accepts_conventional_commits(#[case] message: &str) {
+        assert_eq!(linter(LintConfig::default()).check(message), Ok(()))
+    }
+
+    #[rstest]
+    #[case("", ParseErrorKind::EmptyMessage)]
+    #[case("Hello world", ParseErrorKind::MissingType)]
+    #[case("feat(scope: Hello world", ParseErrorKind::InvalidScope)]
+    #[case("feat:Hello world", ParseErrorKind::InvalidSubject)]
+    fn rejects_non_conventional_commits(#[case] message: &str, #[case] expected: ParseErrorKind) {
+        match linter(LintConfig::default()).check(message) {
+            Err(Violation::NotConventional(err)) => assert_eq!(err.kind, expected),
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
+    #[rstest]
+    #[case("feat: Hello world", true)]
+    #[case("fix: Hello world", true)]
+    #[case("chore: Hello world", true)]
+    #[case("Docs: Hello world", true)]
+    #[case("style: Hello world", false)]
+    fn checks_allowed_types(#[case] message: &str, #[case] expected_valid: bool) {
+        let linter = linter(LintConfig {
+            types: vec![String::from("chore"), String::from("docs")],
+            ..LintConfig::default()
+        });
+
+        assert_eq!(linter.check(message).is_ok(), expected_valid)
+    }
+
+    #[rstest]
+    #[case("feat: Hello world", true)]
+    #[case("feat(cli): Hello world", true)]
+    #[case("feat(lib): Hello world", false)]
+    fn checks_allowed_scopes(#[case] message: &str, #[case] expected_valid: bool) {
+        let linter = linter(LintConfig {
+            scopes: vec![String::from("cli")],
+            ..LintConfig::default()
+        });
+
+        assert_eq!(linter.check(message).is_ok(), expected_valid)
+    }
+
+    #[rstest]
+    #[case("feature: Hello world", true)]
+    #[case("Bugfix: Hello world", true)]
+    #[case("Update the readme", true)]
+    #[case("style: Hello world", false)]
+    fn resolves_types_like_the_release(#[case] message: &str, #[case] expected_valid: bool) {
+        let mut linter = linter(LintConfig {
+            types: vec![String::from("chore")],
+            ..LintConfig::default()
+        });
+        linter.rules.add_type_alias("feature", ChangeType::Feature);
+        linter.rules.add_type_alias("bugfix", ChangeType::Fix);
+        linter
+            .rules
+            .add_fallback("^Update ", ChangeType::Custom("chore"), false)
+            .unwrap();
+
+        assert_eq!(linter.check(message).is_ok(), expected_valid)
+    }
+
+    #[rstest]
+    #[case("feat(CLI): Hello world", true)]
+    #[case("feat(command-line): Hello world", true)]
+    #[case("feat(Lib): Hello world", true)]
+    #[case("feat(core): Hello world", false)]
+    fn resolves_scopes_like_the_release(#[case] message: &str, #[case] expected_valid: bool) {
+        let mut linter = linter(LintConfig {
+            scopes: vec![String::from("cli"), String::from("lib")],
+            ..LintConfig::default()
+        });
+        linter.scope_names.set_ignore_case(true);
+        linter.scope_names.add_alias("command-line", "cli");
+
+        assert_eq!(linter.check(message).is_ok(), expected_valid)
+    }
+
+    #[test]
+    fn strips_git_comments() {
+        assert_eq!(
+            strip_comments("feat: Hello\n# Please enter the commit message\n\nbody"),
+            "feat: Hello\n\nbody"
+        )
+    }
+
+    #[test]
+    fn strips_verbose_diff() {
+        assert_eq!(
+            strip_comments(
+                "feat: Hello\n\nbody\n# ------------------------ >8 ------------------------\n# Do not modify or remove the line above.\ndiff --git a/a b/a\n+x"
+            ),
+            "feat: Hello\n\nbody"
+        )
+    }
+}
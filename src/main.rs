@@ -2,81 +2,172 @@
 #![warn(nonstandard_style, rust_2018_idioms)]
 #![allow(clippy::enum_variant_names)]
 
+use std::env;
 use std::error::Error;
 use std::fmt;
 use std::fmt::{Display, Formatter};
 use std::process;
 
 use git2::Repository;
-use semver::Version;
+use semver::{Prerelease, Version};
 
-use autorel_chlg::git::ChangeLogRepository;
+use autorel_chlg::git::{ChangeLogRepository, LoadedChangeLog, ParsedCommit, SkippedCommit};
+use autorel_chlg::ParsingRules;
 
 use crate::bump::Bump;
-use crate::cli::Opts;
+use crate::cli::{Command, Opts};
 use crate::config::Config;
+use crate::preflight::Failure;
 use crate::release::Release;
 
-mod action;
 mod bump;
+mod cargo;
 mod changelog;
 mod cli;
 mod cmd;
 mod config;
 mod git;
 mod github;
+mod lint;
+mod metadata;
+mod preflight;
 mod release;
+mod sign;
+#[cfg(test)]
+mod test_repo;
+mod version_file;
 
 fn main() {
     let options = cli::parse();
 
-    match run(&options) {
-        Ok(None) => println!("Nothing to release"),
-        Ok(Some(Release { version, .. })) => {
+    let result = match &options.command {
+        Some(Command::Lint(lint_options)) => lint::run(&options.config, lint_options),
+        None => release(&options),
+    };
+
+    if let Err(err) = result {
+        eprintln!("\n\n{}", err);
+        process::exit(1);
+    }
+}
+
+fn release(options: &Opts) -> Result<(), Box<dyn Error>> {
+    match run(options)? {
+        None => println!("Nothing to release"),
+        Some(Release { version, .. }) => {
             if !options.dry_run {
                 println!("\n\nVersion {} successfully released", version)
             }
         }
-        Err(err) => {
-            eprintln!("\n\n{}", err);
-            process::exit(1);
-        }
     }
+    Ok(())
 }
 
 fn run(options: &Opts) -> Result<Option<Release<Version>>, Box<dyn Error>> {
     let config: Config = config::read(&options.config)?;
 
-    match find_next_release(&config.tag_prefix)? {
+    match find_next_release(&config, options)? {
         None => Ok(None),
         Some(mut release) => {
             if release.prev_version.is_none() && !options.force {
                 Err(Box::new(PreviousReleaseNotFound))
-            } else if !release.changelog.has_feature_or_fix() && !options.force {
-                Ok(None)
             } else {
+                if let Some(level) = options.bump {
+                    let prev_version = release
+                        .prev_version
+                        .as_ref()
+                        .ok_or(BumpWithoutPreviousVersion)?;
+                    let minimum = prev_version.clone().bumped_level(level);
+                    if minimum > release.version {
+                        release.version = minimum;
+                    }
+                }
                 if options.stable {
                     release.version.stabilize()
                 }
-                perform_release(&config, &release, options.dry_run)?;
+                let prerelease = release
+                    .branch_config
+                    .as_ref()
+                    .and_then(|it| it.prerelease.as_deref());
+                if let Some(identifier) = prerelease {
+                    // An explicit pre-release (from --release-as or Release-As) is kept as is
+                    if release.version.pre.is_empty() {
+                        let released = git::find_releases(&release.repo, &config.tag_prefix)?;
+                        release.version.pre =
+                            bump::prerelease(&release.version, identifier, &released)?;
+                    }
+                }
+                append_build_metadata(&config, &mut release)?;
+                if let Some(prev_version) = &release.prev_version {
+                    if precedence(&release.version) <= precedence(prev_version) {
+                        return Err(Box::new(VersionNotGreater {
+                            version: release.version,
+                            prev_version: prev_version.clone(),
+                        }));
+                    }
+                }
+                let mut failures = check_release_allowed(&config, &release)?;
+                failures.extend(preflight::check(
+                    &release.repo,
+                    &config.git,
+                    &config.tag_name(&release.version),
+                    options.dry_run,
+                )?);
+                if !failures.is_empty() {
+                    if options.dry_run {
+                        println!("{}", preflight::Error(failures));
+                    } else {
+                        return Err(Box::new(preflight::Error(failures)));
+                    }
+                }
+                perform_release(&config, &mut release, options.dry_run)?;
                 Ok(Some(release))
             }
         }
     }
 }
 
+/// Appends the configured build metadata to the version, unless it already has some (e.g. from `--release-as`)
+///
+/// The `{{sha}}` is the commit the release is computed from (the parent of the release commit),
+/// because the version is written in the release commit itself.
+fn append_build_metadata(
+    config: &Config,
+    release: &mut Release<Version>,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(template) = &config.versioning.build_metadata {
+        if release.version.build.is_empty() {
+            let head = release.repo.head()?.peel_to_commit()?.id().to_string();
+            release.version.build = metadata::render(template, &head, |name| env::var(name).ok())?;
+        }
+    }
+    Ok(())
+}
+
 fn perform_release(
     config: &Config,
-    release: &Release<Version>,
+    release: &mut Release<Version>,
     dry_run: bool,
 ) -> Result<(), Box<dyn Error>> {
     let version_str = release.version.to_string();
+    let tag_name = config.tag_name(&release.version);
+    let channel = release
+        .branch_config
+        .as_ref()
+        .and_then(|it| it.channel.as_deref())
+        .unwrap_or_default();
     let title_suffix = if dry_run { " [DRY RUN]" } else { "" };
     println!("Releasing version {}{}", version_str, title_suffix);
 
     if !config.hooks.verify.is_empty() {
         println!("\nVerifying{}", title_suffix);
-        cmd::execute_all(&config.hooks.verify, &version_str, dry_run)?;
+        cmd::execute_all(&config.hooks.verify, &version_str, channel, dry_run)?;
+    }
+
+    if config.notes.contributors && !dry_run {
+        if let Some(gh_config) = &config.github {
+            github::resolve_handles(gh_config, &mut release.changelog);
+        }
     }
 
     if config.changelog {
@@ -84,30 +175,55 @@ fn perform_release(
         changelog::generate(release, dry_run)?;
     }
 
+    let mut commit_config = config.commit.clone();
+    if !config.version_files.is_empty() || config.cargo.is_some() {
+        println!("\nUpdating version files{}", title_suffix);
+        let mut updated =
+            version_file::update_all(&config.version_files()?, &version_str, dry_run)?;
+
+        if let Some(cargo_config) = &config.cargo {
+            updated.extend(cargo::release(
+                config,
+                cargo_config,
+                &release.repo,
+                release.from.as_deref(),
+                &release.version,
+                dry_run,
+            )?);
+        }
+
+        for path in updated {
+            if !commit_config.files.contains(&path) {
+                commit_config.files.push(path);
+            }
+        }
+    }
+
     if !config.hooks.prepare.is_empty() {
         println!("\nPreparing{}", title_suffix);
-        cmd::execute_all(&config.hooks.prepare, &version_str, dry_run)?;
+        cmd::execute_all(&config.hooks.prepare, &version_str, channel, dry_run)?;
     }
 
     println!("\nUpdating git repository{}", title_suffix);
     git::commit(
         &release.repo,
-        &config.commit,
-        &config.tag_prefix,
+        &commit_config,
+        &config.git,
+        &tag_name,
         &version_str,
         dry_run,
     )?;
 
     if !config.hooks.publish.is_empty() {
         println!("\nPublishing{}", title_suffix);
-        cmd::execute_all(&config.hooks.publish, &version_str, dry_run)?;
+        cmd::execute_all(&config.hooks.publish, &version_str, channel, dry_run)?;
     }
 
     if let Some(gh_config) = &config.github {
         println!("\nCreate github release{}", title_suffix);
         github::create_github_release(
             gh_config,
-            &config.tag_prefix,
+            &tag_name,
             version_str,
             &release.changelog,
             dry_run,
@@ -117,33 +233,208 @@ fn perform_release(
     Ok(())
 }
 
-fn find_next_release(tag_prefix: &str) -> Result<Option<Release<Version>>, git::Error> {
+fn find_next_release(
+    config: &Config,
+    opts: &Opts,
+) -> Result<Option<Release<Version>>, Box<dyn Error>> {
     let repo = Repository::open(".")?;
-    let release = match git::find_latest_release::<Version>(&repo, "v")? {
-        None => {
-            let changelog = repo.load_changelog(None)?;
-
-            changelog.semver_scope().map(|_| Release {
-                prev_version: None,
-                version: Version::new(0, 1, 0),
-                changelog,
-                repo,
-            })
+    let options = config.load_options()?;
+    let changelog = config
+        .changelog()?
+        .with_bodies(config.notes.bodies.into())
+        .with_migration_guide(config.notes.migration_guide)
+        .with_contributors(config.notes.contributors);
+
+    let (prev_version, from) = find_previous_release(&repo, config, opts)?;
+    let branch = git::current_branch(&repo)?;
+    let branch_config = branch.as_deref().and_then(|it| config.branch(it)).cloned();
+
+    let LoadedChangeLog {
+        changelog,
+        commits,
+        skipped,
+    } = repo.load_changelog_with(changelog, from.as_deref(), &options)?;
+
+    if opts.dry_run {
+        print_skipped_commits(&skipped);
+    }
+
+    let stabilize = match &config.versioning.stabilize_footer {
+        None => false,
+        Some(token) => !footer_values(&commits, &options.rules, token).is_empty(),
+    };
+
+    let mut release_as: Option<Version> = None;
+    for (commit, value) in footer_values(&commits, &options.rules, "Release-As") {
+        let version = value.parse().map_err(|error| InvalidReleaseAs {
+            commit: commit.clone(),
+            value: value.clone(),
+            error,
+        })?;
+        release_as = release_as.max(Some(version));
+    }
+
+    let computed = changelog
+        .semver_scope()
+        .filter(|_| changelog.has_feature_or_fix() || opts.force)
+        .map(|scope| {
+            let mut version = match &prev_version {
+                None => Version::new(0, 1, 0),
+                Some(prev_version) => prev_version
+                    .clone()
+                    .bumped(scope, config.versioning.pre_stable.into()),
+            };
+            if stabilize {
+                version.stabilize();
+            }
+            version
+        });
+
+    let release =
+        next_version(computed, release_as, opts.release_as.as_ref()).map(|version| Release {
+            version,
+            prev_version,
+            from,
+            changelog,
+            repo,
+            branch,
+            branch_config,
+        });
+
+    Ok(release)
+}
+
+/// Chooses the version to release: the `--release-as` option, or else the `Release-As` footer
+/// (both even if there is no feature or fix to release), or else the version computed from the changes
+fn next_version(
+    computed: Option<Version>,
+    footer: Option<Version>,
+    forced: Option<&Version>,
+) -> Option<Version> {
+    match (forced, footer) {
+        (Some(forced), footer) => {
+            if let Some(footer) = footer.filter(|it| it != forced) {
+                println!(
+                    "The Release-As footer ({}) is overridden by --release-as {}",
+                    footer, forced
+                );
+            }
+            Some(forced.clone())
+        }
+        (None, Some(footer)) => Some(footer),
+        (None, None) => computed,
+    }
+}
+
+/// Verifies that the current branch is allowed to release, that the version isn't already released
+/// (e.g. from another branch), and that the bump level is allowed on the current branch
+///
+/// Returns the failures, to report them together with the ones of the pre-flight checks.
+fn check_release_allowed(
+    config: &Config,
+    release: &Release<Version>,
+) -> Result<Vec<Failure>, git::Error> {
+    let mut failures = Vec::new();
+
+    if !config.branches.is_empty() && release.branch_config.is_none() {
+        failures.push(Failure::BranchNotAllowed(release.branch.clone()));
+    }
+
+    let released = git::find_releases::<Version>(&release.repo, &config.tag_prefix)?;
+    if released
+        .iter()
+        .any(|version| precedence(version) == precedence(&release.version))
+    {
+        failures.push(Failure::VersionAlreadyReleased(release.version.clone()));
+    }
+
+    let max_bump = release.branch_config.as_ref().and_then(|it| it.max_bump);
+    if let (Some(max_bump), Some(prev_version)) = (max_bump, &release.prev_version) {
+        let level = bump::level(prev_version, &release.version);
+        if level > max_bump {
+            failures.push(Failure::BumpNotAllowed {
+                branch: release.branch.clone().unwrap_or_default(),
+                level,
+                max_bump,
+            });
         }
-        Some(prev_version) => {
-            let changelog =
-                repo.load_changelog(Some(&format!("{}{}", tag_prefix, prev_version)))?;
-
-            changelog.semver_scope().map(|scope| Release {
-                prev_version: Some(prev_version.clone()),
-                version: prev_version.bumped(scope),
-                changelog,
-                repo,
-            })
+    }
+
+    Ok(failures)
+}
+
+/// Components of the version that define its precedence (ignoring the build metadata)
+fn precedence(version: &Version) -> (u64, u64, u64, &Prerelease) {
+    (version.major, version.minor, version.patch, &version.pre)
+}
+
+/// Returns the commit ids and values of the footers having the given token in the conventional commits
+fn footer_values(
+    commits: &[ParsedCommit],
+    rules: &ParsingRules,
+    token: &str,
+) -> Vec<(String, String)> {
+    commits
+        .iter()
+        .filter_map(|commit| Some((commit, rules.try_parse(&commit.message).ok()?)))
+        .flat_map(|(commit, change)| {
+            change
+                .footer_values(token)
+                .map(|value| (commit.id.clone(), String::from(value)))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Returns the previous version, and the revision from which the changes are released
+///
+/// The previous version is given by the `--from-version` option, or found in the tags, or else read from the baseline file.
+fn find_previous_release(
+    repo: &Repository,
+    config: &Config,
+    opts: &Opts,
+) -> Result<(Option<Version>, Option<String>), Box<dyn Error>> {
+    let baseline = config.baseline()?;
+
+    let prev_version = match &opts.from_version {
+        Some(version) => Some(version.clone()),
+        None => match git::find_latest_release::<Version>(repo, &config.tag_prefix)? {
+            Some(version) => Some(version),
+            None => match &baseline {
+                Some(file) => Some(file.current_version()?.parse()?),
+                None => None,
+            },
+        },
+    };
+
+    let from = match &prev_version {
+        None => None,
+        Some(version) => {
+            let tag = config.tag_name(version);
+            if repo.revparse_single(&tag).is_ok() {
+                Some(tag)
+            } else if let Some(file) = &baseline {
+                git::find_version_commit(repo, file, &version.to_string())?
+                    .map(|commit| commit.to_string())
+            } else {
+                None
+            }
         }
     };
 
-    Ok(release)
+    Ok((prev_version, from))
+}
+
+fn print_skipped_commits(skipped: &[SkippedCommit]) {
+    if skipped.is_empty() {
+        return;
+    }
+
+    println!("Skipped commits (not recognized as conventional commits):");
+    for commit in skipped {
+        println!("> {:.7} {}\n  {}", commit.id, commit.summary, commit.error);
+    }
+    println!();
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -157,3 +448,204 @@ impl Display for PreviousReleaseNotFound {
 }
 
 impl std::error::Error for PreviousReleaseNotFound {}
+
+#[derive(Debug, Copy, Clone)]
+struct BumpWithoutPreviousVersion;
+
+impl Display for BumpWithoutPreviousVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Cannot apply `--bump` without a previous version. Use `--from-version` to give it, or `--release-as` to choose the version to release.")
+    }
+}
+
+impl std::error::Error for BumpWithoutPreviousVersion {}
+
+#[derive(Debug, Clone)]
+struct VersionNotGreater {
+    version: Version,
+    prev_version: Version,
+}
+
+impl Display for VersionNotGreater {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Cannot release version {}, because it isn't greater than the previous version {}",
+            self.version, self.prev_version
+        )
+    }
+}
+
+impl std::error::Error for VersionNotGreater {}
+
+#[derive(Debug)]
+struct InvalidReleaseAs {
+    commit: String,
+    value: String,
+    error: semver::Error,
+}
+
+impl Display for InvalidReleaseAs {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid `Release-As` footer '{}' in commit {:.7}: {}",
+            self.value, self.commit, self.error
+        )
+    }
+}
+
+impl std::error::Error for InvalidReleaseAs {}
+
+#[cfg(test)]
+mod tests {
+    use clap::Clap;
+    use rstest::rstest;
+
+    use autorel_chlg::ChangeLog;
+
+    use crate::cli::BumpLevel;
+    use crate::test_repo::TestRepo;
+
+    use super::*;
+
+    #[test]
+    fn release_cargo_workspace_since_baseline_commit() {
+        let test = TestRepo::new();
+        let baseline = test.commit_files(
+            "chore: release 1.0.0",
+            &[
+                (
+                    "Cargo.toml",
+                    "[workspace]\nmembers = [\"lib\"]\n\n[package]\nname = \"app\"\nversion = \"1.0.0\"\n",
+                ),
+                (
+                    "lib/Cargo.toml",
+                    "[package]\nname = \"lib\"\nversion = \"0.1.0\"\n",
+                ),
+            ],
+        );
+        test.commit_files("feat: new feature", &[("lib/src/lib.rs", "")]);
+        let config_path = test.path().join("release.yml");
+        std::fs::write(
+            &config_path,
+            format!(
+                "baseline:\n  path: Cargo.toml\ncargo:\n  manifest: {}\n",
+                test.path().join("Cargo.toml").display()
+            ),
+        )
+        .unwrap();
+        let config = config::read(&config_path).expect("Invalid configuration");
+        let opts = Opts::parse_from(["autorel", "--from-version", "1.0.0"]);
+
+        let (prev_version, from) =
+            find_previous_release(&test.repo, &config, &opts).expect("Failed to find release");
+        assert_eq!(prev_version, Some(Version::new(1, 0, 0)));
+        assert_eq!(from, Some(baseline.to_string()));
+
+        let updated = cargo::release(
+            &config,
+            config.cargo.as_ref().unwrap(),
+            &test.repo,
+            from.as_deref(),
+            &Version::new(1, 1, 0),
+            false,
+        )
+        .expect("Failed to release the workspace");
+
+        assert!(updated.contains(&test.path().join("lib/Cargo.toml")));
+        assert_eq!(
+            std::fs::read_to_string(test.path().join("lib/Cargo.toml")).unwrap(),
+            "[package]\nname = \"lib\"\nversion = \"0.1.1\"\n"
+        );
+    }
+
+    #[rstest]
+    #[case(Some("1.3.0"), None, None, Some("1.3.0"))]
+    #[case(Some("1.3.0"), Some("2.0.0"), None, Some("2.0.0"))]
+    #[case(None, Some("2.0.0"), None, Some("2.0.0"))]
+    #[case(Some("1.3.0"), Some("2.0.0"), Some("3.0.0-rc.1"), Some("3.0.0-rc.1"))]
+    #[case(None, None, Some("1.2.4"), Some("1.2.4"))]
+    fn choose_next_version(
+        #[case] computed: Option<&str>,
+        #[case] footer: Option<&str>,
+        #[case] forced: Option<&str>,
+        #[case] expected: Option<&str>,
+    ) {
+        let parse = |it: Option<&str>| it.map(|it| Version::parse(it).unwrap());
+
+        assert_eq!(
+            next_version(parse(computed), parse(footer), parse(forced).as_ref()),
+            parse(expected)
+        );
+    }
+
+    #[test]
+    fn report_all_disallowed_releases() {
+        let test = TestRepo::new();
+        let commit = test.commit_files("feat: first", &[("a", "a")]);
+        test.tag("v1.1.0", commit);
+        let config_path = test.path().join("release.yml");
+        std::fs::write(
+            &config_path,
+            "branches:\n  - name: main\n    max_bump: patch\n",
+        )
+        .unwrap();
+        let config = config::read(&config_path).expect("Invalid configuration");
+        let release = Release {
+            prev_version: Some(Version::new(1, 0, 0)),
+            from: None,
+            version: Version::new(1, 1, 0),
+            changelog: ChangeLog::default(),
+            repo: test.repo,
+            branch: Some(String::from("main")),
+            branch_config: config.branch("main").cloned(),
+        };
+
+        assert_eq!(
+            check_release_allowed(&config, &release).unwrap(),
+            vec![
+                Failure::VersionAlreadyReleased(Version::new(1, 1, 0)),
+                Failure::BumpNotAllowed {
+                    branch: String::from("main"),
+                    level: BumpLevel::Minor,
+                    max_bump: BumpLevel::Patch,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn build_metadata_sha_is_the_parent_of_the_tagged_commit() {
+        let test = TestRepo::new();
+        let head = test.commit_files("feat: first", &[("a", "a")]);
+        let mut config_file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(
+            &mut config_file,
+            b"changelog: false\nversioning:\n  build_metadata: \"{{sha}}\"\ngit:\n  push: false\n",
+        )
+        .unwrap();
+        let config = config::read(config_file.path()).expect("Invalid configuration");
+        let mut release = Release {
+            prev_version: Some(Version::new(1, 0, 0)),
+            from: None,
+            version: Version::new(1, 1, 0),
+            changelog: ChangeLog::default(),
+            repo: test.repo,
+            branch: None,
+            branch_config: None,
+        };
+
+        append_build_metadata(&config, &mut release).expect("Failed to render metadata");
+        perform_release(&config, &mut release, false).expect("Failed to release");
+
+        let tagged = release
+            .repo
+            .revparse_single("v1.1.0")
+            .unwrap()
+            .peel_to_commit()
+            .unwrap();
+        assert_eq!(tagged.parent_id(0).unwrap(), head);
+        assert_eq!(release.version.build.as_str(), &head.to_string()[..7]);
+    }
+}
@@ -0,0 +1,105 @@
+use std::fmt::{self, Display, Formatter};
+
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use semver::BuildMetadata;
+
+lazy_static! {
+    static ref PLACEHOLDER_REGEX: Regex = Regex::new(r"\{\{\s*([\w.]+)\s*\}\}").unwrap();
+}
+
+/// Renders the build metadata template of the configuration
+///
+/// `{{sha}}` is replaced by the abbreviated id of the commit the release is computed from
+/// (the parent of the release commit),
+/// and `{{env.NAME}}` by the value of the environment variable `NAME`.
+pub fn render(
+    template: &str,
+    sha: &str,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<BuildMetadata, Error> {
+    let mut missing = None;
+    let rendered = PLACEHOLDER_REGEX.replace_all(template, |captures: &Captures<'_>| {
+        let name = &captures[1];
+        let value = match name.strip_prefix("env.") {
+            Some(variable) => env(variable),
+            None if name == "sha" => Some(sha.chars().take(7).collect()),
+            None => None,
+        };
+        value.unwrap_or_else(|| {
+            missing.get_or_insert_with(|| String::from(name));
+            String::new()
+        })
+    });
+
+    if let Some(name) = missing {
+        return Err(Error::UnknownPlaceholder(name));
+    }
+
+    BuildMetadata::new(&rendered).map_err(|err| Error::InvalidMetadata(rendered.into_owned(), err))
+}
+
+#[derive(Debug)]
+pub enum Error {
+    UnknownPlaceholder(String),
+    InvalidMetadata(String, semver::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnknownPlaceholder(name) => write!(
+                f,
+                "Cannot render the build metadata: '{{{{{}}}}}' is unknown or not set",
+                name
+            ),
+            Error::InvalidMetadata(metadata, err) => {
+                write!(f, "Invalid build metadata '{}': {}", metadata, err)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    fn env(name: &str) -> Option<String> {
+        match name {
+            "CI_PIPELINE_ID" => Some(String::from("42")),
+            _ => None,
+        }
+    }
+
+    #[rstest]
+    #[case("git.{{sha}}", "git.abc1234")]
+    #[case("build.{{env.CI_PIPELINE_ID}}", "build.42")]
+    #[case("build.{{ env.CI_PIPELINE_ID }}.{{sha}}", "build.42.abc1234")]
+    #[case("nightly", "nightly")]
+    fn render_placeholders(#[case] template: &str, #[case] expected: &str) {
+        let metadata = render(template, "abc1234def", env).expect("Failed to render");
+        assert_eq!(metadata.as_str(), expected);
+    }
+
+    #[rstest]
+    #[case("build.{{env.UNKNOWN}}")]
+    #[case("{{version}}")]
+    fn unknown_placeholder(#[case] template: &str) {
+        assert!(matches!(
+            render(template, "abc1234", env),
+            Err(Error::UnknownPlaceholder(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_metadata() {
+        assert!(matches!(
+            render("build_{{sha}}", "abc1234", env),
+            Err(Error::InvalidMetadata(_, _))
+        ));
+    }
+}
@@ -0,0 +1,367 @@
+use std::fmt::{self, Display, Formatter};
+
+use git2::{BranchType, Repository};
+use semver::Version;
+
+use crate::cli::BumpLevel;
+use crate::config::GitConfig;
+use crate::git;
+
+/// Problem of the repository that would make the release fail halfway, or that the configuration doesn't allow
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Failure {
+    BranchNotAllowed(Option<String>),
+    VersionAlreadyReleased(Version),
+    BumpNotAllowed {
+        branch: String,
+        level: BumpLevel,
+        max_bump: BumpLevel,
+    },
+    DirtyWorkingTree(Vec<String>),
+    DetachedHead,
+    BehindUpstream {
+        upstream: String,
+        behind: usize,
+    },
+    TagExists(String),
+    RemoteTagExists {
+        remote: String,
+        tag: String,
+    },
+    RemoteNotFound(String),
+    CannotListRemoteTags {
+        remote: String,
+        error: String,
+    },
+    ShallowClone,
+}
+
+/// Verifies that the repository is ready for the release of the tag, before anything is done
+///
+/// Returns all the failures found.
+/// In dry-run, the tags of the remote aren't fetched (the remote may not be reachable from a local run).
+pub fn check(
+    repo: &Repository,
+    config: &GitConfig,
+    tag_name: &str,
+    dry_run: bool,
+) -> Result<Vec<Failure>, git::Error> {
+    let mut failures = Vec::new();
+
+    if repo.is_shallow() {
+        failures.push(Failure::ShallowClone);
+    }
+
+    let changed = git::changed_files(repo)?;
+    if !changed.is_empty() {
+        failures.push(Failure::DirtyWorkingTree(changed));
+    }
+
+    match git::current_branch(repo)? {
+        None => failures.push(Failure::DetachedHead),
+        Some(branch) => {
+            // The branch that receives the release commit: the configured push branch, or else the upstream
+            let upstream = match &config.push_branch {
+                Some(push_branch) => repo
+                    .find_branch(
+                        &format!("{}/{}", config.remote, push_branch),
+                        BranchType::Remote,
+                    )
+                    .ok(),
+                None => repo
+                    .find_branch(&branch, BranchType::Local)?
+                    .upstream()
+                    .ok(),
+            };
+            // Compared to the remote-tracking branch as last fetched: commits pushed since then aren't detected
+            // (the push of the release then fails, after the release commit and tag are created locally)
+            if let Some(upstream) = upstream {
+                let (_, behind) = repo.graph_ahead_behind(
+                    repo.head()?.peel_to_commit()?.id(),
+                    upstream.get().peel_to_commit()?.id(),
+                )?;
+                if behind > 0 {
+                    failures.push(Failure::BehindUpstream {
+                        upstream: upstream.name()?.unwrap_or_default().to_owned(),
+                        behind,
+                    });
+                }
+            }
+        }
+    }
+
+    if repo
+        .find_reference(&format!("refs/tags/{}", tag_name))
+        .is_ok()
+    {
+        failures.push(Failure::TagExists(tag_name.to_owned()));
+    }
+
+    // Without push, the remote may not be reachable until the later step that pushes
+    if config.push {
+        check_remote(repo, config, tag_name, dry_run, &mut failures);
+    }
+
+    Ok(failures)
+}
+
+fn check_remote(
+    repo: &Repository,
+    config: &GitConfig,
+    tag_name: &str,
+    dry_run: bool,
+    failures: &mut Vec<Failure>,
+) {
+    if repo.find_remote(&config.remote).is_err() {
+        failures.push(Failure::RemoteNotFound(config.remote.clone()));
+    } else if !dry_run {
+        match git::remote_tag_exists(repo, config, tag_name) {
+            Ok(false) => (),
+            Ok(true) => failures.push(Failure::RemoteTagExists {
+                remote: config.remote.clone(),
+                tag: tag_name.to_owned(),
+            }),
+            Err(err) => failures.push(Failure::CannotListRemoteTags {
+                remote: config.remote.clone(),
+                error: err.to_string(),
+            }),
+        }
+    }
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::BranchNotAllowed(None) => write!(
+                f,
+                "The HEAD is detached, but the releases are restricted to the configured `branches`."
+            ),
+            Failure::BranchNotAllowed(Some(branch)) => write!(
+                f,
+                "The branch '{}' isn't one of the configured `branches`.",
+                branch
+            ),
+            Failure::VersionAlreadyReleased(version) => write!(
+                f,
+                "The version {} has already been released (from another branch?).",
+                version
+            ),
+            Failure::BumpNotAllowed {
+                branch,
+                level,
+                max_bump,
+            } => write!(
+                f,
+                "The branch '{}' only allows up to {} bumps, but this is a {} version bump.",
+                branch,
+                format!("{:?}", max_bump).to_lowercase(),
+                format!("{:?}", level).to_lowercase()
+            ),
+            Failure::DirtyWorkingTree(files) => write!(
+                f,
+                "The working tree isn't clean ({}). Commit, stash or ignore the changes before releasing.",
+                files.join(", ")
+            ),
+            Failure::DetachedHead => write!(
+                f,
+                "The HEAD is detached. Checkout the branch to release (the release commit is pushed to it)."
+            ),
+            Failure::BehindUpstream { upstream, behind } => write!(
+                f,
+                "The branch is {} commit(s) behind '{}'. Pull the changes before releasing.",
+                behind, upstream
+            ),
+            Failure::TagExists(tag) => write!(
+                f,
+                "The tag '{}' already exists. Delete it if it wasn't actually released.",
+                tag
+            ),
+            Failure::RemoteTagExists { remote, tag } => write!(
+                f,
+                "The tag '{}' already exists on '{}'. Fetch the tags to compute the next version from it.",
+                tag, remote
+            ),
+            Failure::RemoteNotFound(remote) => write!(
+                f,
+                "The remote '{}' doesn't exist. Add it, or configure the remote to push to (`git.remote`).",
+                remote
+            ),
+            Failure::CannotListRemoteTags { remote, error } => write!(
+                f,
+                "Cannot list the tags of '{}' ({}). Make sure the remote is reachable with the configured credentials.",
+                remote, error
+            ),
+            Failure::ShallowClone => write!(
+                f,
+                "The repository is a shallow clone, that may miss commits and tags. Fetch all the history (e.g. `git fetch --unshallow --tags`)."
+            ),
+        }
+    }
+}
+
+/// Failures of the pre-flight checks, reported all together
+#[derive(Debug, Clone)]
+pub struct Error(pub Vec<Failure>);
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Cannot release:")?;
+        for failure in &self.0 {
+            writeln!(f, "* {}", failure)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_repo::TestRepo;
+
+    use super::*;
+
+    #[test]
+    fn clean_repository() {
+        let (test, _remote) = TestRepo::with_remote();
+        test.commit_files("chore: test", &[("a", "a")]);
+
+        assert_eq!(
+            check(&test.repo, &GitConfig::default(), "v1.0.0", false).unwrap(),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn report_all_failures() {
+        let (test, remote) = TestRepo::with_remote();
+        let commit = test.commit_files("chore: test", &[("a", "a")]);
+        test.repo.set_head_detached(commit).unwrap();
+        std::fs::write(test.path().join("b"), "b").unwrap();
+        test.tag("v1.0.0", commit);
+        let remote_commit = remote.commit_tree(
+            "chore: test",
+            remote.repo.treebuilder(None).unwrap().write().unwrap(),
+        );
+        remote.tag("v1.0.0", remote_commit);
+
+        assert_eq!(
+            check(&test.repo, &GitConfig::default(), "v1.0.0", false).unwrap(),
+            vec![
+                Failure::DirtyWorkingTree(vec![String::from("b")]),
+                Failure::DetachedHead,
+                Failure::TagExists(String::from("v1.0.0")),
+                Failure::RemoteTagExists {
+                    remote: String::from("origin"),
+                    tag: String::from("v1.0.0"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn behind_upstream() {
+        let (test, _remote) = TestRepo::with_remote();
+        let first = test.commit_files("chore: test", &[("a", "a")]);
+        let second = test.commit_files("chore: test", &[("b", "b")]);
+        test.repo
+            .reference("refs/remotes/origin/main", second, true, "test")
+            .unwrap();
+        let mut branch = test
+            .repo
+            .branch("main", &test.repo.find_commit(first).unwrap(), true)
+            .unwrap();
+        test.repo.set_head("refs/heads/main").unwrap();
+        test.repo
+            .checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+            .unwrap();
+        branch.set_upstream(Some("origin/main")).unwrap();
+
+        assert_eq!(
+            check(&test.repo, &GitConfig::default(), "v1.0.0", false).unwrap(),
+            vec![Failure::BehindUpstream {
+                upstream: String::from("origin/main"),
+                behind: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn behind_push_branch() {
+        let (test, _remote) = TestRepo::with_remote();
+        let first = test.commit_files("chore: test", &[("a", "a")]);
+        let second = test.commit_files("chore: test", &[("b", "b")]);
+        test.repo
+            .reference("refs/remotes/origin/releases", second, true, "test")
+            .unwrap();
+        test.repo
+            .branch("main", &test.repo.find_commit(first).unwrap(), true)
+            .unwrap();
+        test.repo.set_head("refs/heads/main").unwrap();
+        test.repo
+            .checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+            .unwrap();
+        let config = GitConfig {
+            push_branch: Some(String::from("releases")),
+            ..GitConfig::default()
+        };
+
+        assert_eq!(
+            check(&test.repo, &config, "v1.0.0", true).unwrap(),
+            vec![Failure::BehindUpstream {
+                upstream: String::from("origin/releases"),
+                behind: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn remote_is_ignored_without_push() {
+        let (test, _remote) = TestRepo::with_remote();
+        test.commit_files("chore: test", &[("a", "a")]);
+        let config = GitConfig {
+            remote: String::from("upstream"),
+            push: false,
+            ..GitConfig::default()
+        };
+
+        assert_eq!(check(&test.repo, &config, "v1.0.0", false).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn unknown_remote() {
+        let (test, _remote) = TestRepo::with_remote();
+        test.commit_files("chore: test", &[("a", "a")]);
+        let config = GitConfig {
+            remote: String::from("upstream"),
+            ..GitConfig::default()
+        };
+
+        assert_eq!(
+            check(&test.repo, &config, "v1.0.0", false).unwrap(),
+            vec![Failure::RemoteNotFound(String::from("upstream"))]
+        );
+    }
+
+    #[test]
+    fn remote_tags_are_not_fetched_in_dry_run() {
+        let (test, remote) = TestRepo::with_remote();
+        test.commit_files("chore: test", &[("a", "a")]);
+        let remote_commit = remote.commit_tree(
+            "chore: test",
+            remote.repo.treebuilder(None).unwrap().write().unwrap(),
+        );
+        remote.tag("v1.0.0", remote_commit);
+
+        assert_eq!(
+            check(&test.repo, &GitConfig::default(), "v1.0.0", true).unwrap(),
+            vec![]
+        );
+        assert!(test
+            .repo
+            .references_glob("refs/autorel/*")
+            .unwrap()
+            .next()
+            .is_none());
+    }
+}
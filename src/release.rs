@@ -2,9 +2,20 @@ use git2::Repository;
 
 use autorel_chlg::ChangeLog;
 
+use crate::config::BranchConfig;
+
 pub struct Release<V> {
     pub prev_version: Option<V>,
+
+    /// Revision from which the changes are released (tag or commit of the previous version)
+    pub from: Option<String>,
     pub version: V,
     pub changelog: ChangeLog,
     pub repo: Repository,
+
+    /// Name of the checked out branch (`None` if the `HEAD` is detached)
+    pub branch: Option<String>,
+
+    /// Settings of the current branch (`None` if the `HEAD` is detached or the branch isn't configured)
+    pub branch_config: Option<BranchConfig>,
 }
@@ -0,0 +1,266 @@
+use std::env;
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+use serde_derive::Deserialize;
+
+use crate::config::SignConfig;
+
+/// Format of the signatures of the release commit and tag
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SignFormat {
+    Openpgp,
+    Ssh,
+}
+
+/// Signs the release commit or tag by invoking `gpg` or `ssh-keygen`, like git does
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Signer {
+    pub format: SignFormat,
+    pub key: Option<String>,
+    pub program: String,
+}
+
+impl Signer {
+    /// Signer of the release commit, if either the configuration or the git configuration (`commit.gpgsign`) enables it
+    pub fn for_commits(
+        config: &SignConfig,
+        git_config: &git2::Config,
+    ) -> Result<Option<Self>, Error> {
+        Self::resolve(config.commits, "commit.gpgsign", config, git_config)
+    }
+
+    /// Signer of the release tag, if either the configuration or the git configuration (`tag.gpgsign`) enables it
+    pub fn for_tags(config: &SignConfig, git_config: &git2::Config) -> Result<Option<Self>, Error> {
+        Self::resolve(config.tags, "tag.gpgsign", config, git_config)
+    }
+
+    fn resolve(
+        enabled: Option<bool>,
+        git_key: &str,
+        config: &SignConfig,
+        git_config: &git2::Config,
+    ) -> Result<Option<Self>, Error> {
+        let enabled = enabled.unwrap_or_else(|| git_config.get_bool(git_key).unwrap_or(false));
+        if !enabled {
+            return Ok(None);
+        }
+
+        let format = match config.format {
+            Some(format) => format,
+            None => match git_config.get_string("gpg.format").ok().as_deref() {
+                None | Some("openpgp") => SignFormat::Openpgp,
+                Some("ssh") => SignFormat::Ssh,
+                Some(other) => return Err(Error::UnsupportedFormat(other.to_owned())),
+            },
+        };
+
+        let key = config
+            .key
+            .clone()
+            .or_else(|| git_config.get_string("user.signingkey").ok());
+
+        let program = match format {
+            SignFormat::Openpgp => git_config
+                .get_string("gpg.openpgp.program")
+                .or_else(|_| git_config.get_string("gpg.program"))
+                .unwrap_or_else(|_| String::from("gpg")),
+            SignFormat::Ssh => git_config
+                .get_string("gpg.ssh.program")
+                .unwrap_or_else(|_| String::from("ssh-keygen")),
+        };
+
+        Ok(Some(Self {
+            format,
+            key,
+            program,
+        }))
+    }
+
+    /// Returns the armored signature of the content
+    pub fn sign(&self, content: &str) -> Result<String, Error> {
+        let mut command = Command::new(&self.program);
+        match self.format {
+            SignFormat::Openpgp => {
+                command.arg("--status-fd=2").arg("-bsa");
+                if let Some(key) = &self.key {
+                    command.arg("-u").arg(key);
+                }
+            }
+            SignFormat::Ssh => {
+                let key = self.key.as_deref().ok_or(Error::MissingSshKey)?;
+                command
+                    .arg("-Y")
+                    .arg("sign")
+                    .arg("-n")
+                    .arg("git")
+                    .arg("-f")
+                    .arg(expand_home(key));
+            }
+        }
+
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| Error::CannotRunProgram(self.program.clone(), err))?;
+        child
+            .stdin
+            .take()
+            .ok_or_else(|| {
+                Error::CannotRunProgram(self.program.clone(), io::ErrorKind::BrokenPipe.into())
+            })?
+            .write_all(content.as_bytes())
+            .map_err(|err| Error::CannotRunProgram(self.program.clone(), err))?;
+
+        let output = child
+            .wait_with_output()
+            .map_err(|err| Error::CannotRunProgram(self.program.clone(), err))?;
+        let signature = String::from_utf8_lossy(&output.stdout).into_owned();
+        if !output.status.success() || signature.is_empty() {
+            return Err(Error::SigningFailed(
+                self.program.clone(),
+                String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+            ));
+        }
+        Ok(signature)
+    }
+}
+
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), env::var("HOME")) {
+        (Some(path), Ok(home)) => format!("{}/{}", home, path),
+        _ => String::from(path),
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    UnsupportedFormat(String),
+    MissingSshKey,
+    CannotRunProgram(String, io::Error),
+    SigningFailed(String, String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnsupportedFormat(format) => {
+                write!(f, "Unsupported signature format: '{}'", format)
+            }
+            Error::MissingSshKey => write!(
+                f,
+                "Cannot sign with ssh without a key (configure `git.sign.key` or `user.signingkey`)"
+            ),
+            Error::CannotRunProgram(program, err) => {
+                write!(f, "Cannot run '{}' to sign: {}", program, err)
+            }
+            Error::SigningFailed(program, stderr) => {
+                write!(f, "Signing with '{}' failed: {}", program, stderr)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::NamedTempFile;
+
+    use super::*;
+
+    /// Git configuration in a temporary file, deleted when dropped
+    struct TestConfig {
+        _file: NamedTempFile,
+        config: git2::Config,
+    }
+
+    impl TestConfig {
+        fn new(entries: &[(&str, &str)]) -> Self {
+            let file = NamedTempFile::new().expect("Failed to create git config");
+            let mut config = git2::Config::open(file.path()).expect("Failed to open git config");
+            for (key, value) in entries {
+                config.set_str(key, value).unwrap();
+            }
+            Self {
+                _file: file,
+                config,
+            }
+        }
+    }
+
+    #[test]
+    fn unsigned_by_default() {
+        let git = TestConfig::new(&[]);
+
+        assert_eq!(
+            Signer::for_commits(&SignConfig::default(), &git.config).unwrap(),
+            None
+        );
+        assert_eq!(
+            Signer::for_tags(&SignConfig::default(), &git.config).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn use_git_configuration() {
+        let git = TestConfig::new(&[
+            ("commit.gpgsign", "true"),
+            ("gpg.format", "ssh"),
+            ("user.signingkey", "~/.ssh/id_ed25519.pub"),
+        ]);
+
+        assert_eq!(
+            Signer::for_commits(&SignConfig::default(), &git.config).unwrap(),
+            Some(Signer {
+                format: SignFormat::Ssh,
+                key: Some(String::from("~/.ssh/id_ed25519.pub")),
+                program: String::from("ssh-keygen"),
+            })
+        );
+        assert_eq!(
+            Signer::for_tags(&SignConfig::default(), &git.config).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn configuration_overrides_git_configuration() {
+        let git = TestConfig::new(&[
+            ("tag.gpgsign", "true"),
+            ("gpg.format", "ssh"),
+            ("gpg.program", "gpg2"),
+        ]);
+        let config = SignConfig {
+            commits: Some(true),
+            tags: Some(false),
+            format: Some(SignFormat::Openpgp),
+            key: Some(String::from("ABCDEF")),
+        };
+
+        assert_eq!(
+            Signer::for_commits(&config, &git.config).unwrap(),
+            Some(Signer {
+                format: SignFormat::Openpgp,
+                key: Some(String::from("ABCDEF")),
+                program: String::from("gpg2"),
+            })
+        );
+        assert_eq!(Signer::for_tags(&config, &git.config).unwrap(), None);
+    }
+
+    #[test]
+    fn unsupported_format() {
+        let git = TestConfig::new(&[("commit.gpgsign", "true"), ("gpg.format", "x509")]);
+
+        assert!(matches!(
+            Signer::for_commits(&SignConfig::default(), &git.config),
+            Err(Error::UnsupportedFormat(_))
+        ));
+    }
+}
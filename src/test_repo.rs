@@ -0,0 +1,80 @@
+use std::fs;
+use std::path::Path;
+
+use git2::{Oid, Repository, Signature};
+use tempfile::TempDir;
+
+/// Git repository in a temporary directory, deleted when dropped
+pub struct TestRepo {
+    pub dir: TempDir,
+    pub repo: Repository,
+}
+
+impl TestRepo {
+    pub fn new() -> Self {
+        let dir = tempfile::tempdir().expect("Failed to create directory");
+        let repo = Repository::init(dir.path()).expect("Failed to create repository");
+        Self { dir, repo }
+    }
+
+    /// Repository having a bare repository as `origin` remote
+    pub fn with_remote() -> (Self, TestRepo) {
+        let test = Self::new();
+        let remote_dir = tempfile::tempdir().expect("Failed to create directory");
+        let remote = Repository::init_bare(remote_dir.path()).expect("Failed to create remote");
+        test.repo
+            .remote("origin", remote_dir.path().to_str().unwrap())
+            .expect("Failed to add remote");
+        (
+            test,
+            TestRepo {
+                dir: remote_dir,
+                repo: remote,
+            },
+        )
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Writes the files (relative to the repository root) and commits them
+    pub fn commit_files(&self, message: &str, files: &[(&str, &str)]) -> Oid {
+        let mut index = self.repo.index().unwrap();
+        for (file, content) in files {
+            let path = self.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, content).unwrap();
+            index.add_path(Path::new(file)).unwrap();
+        }
+        index.write().unwrap();
+        self.commit_tree(message, index.write_tree().unwrap())
+    }
+
+    /// Commits the tree on top of `HEAD`, without touching the index (e.g. in a bare repository)
+    pub fn commit_tree(&self, message: &str, tree: Oid) -> Oid {
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        let tree = self.repo.find_tree(tree).unwrap();
+        let parent = self
+            .repo
+            .head()
+            .ok()
+            .and_then(|it| it.peel_to_commit().ok());
+        self.repo
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &parent.iter().collect::<Vec<_>>(),
+            )
+            .expect("Failed to commit")
+    }
+
+    pub fn tag(&self, name: &str, oid: Oid) {
+        self.repo
+            .tag_lightweight(name, &self.repo.find_object(oid, None).unwrap(), false)
+            .expect("Failed to tag");
+    }
+}
@@ -0,0 +1,475 @@
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use regex::{Captures, Regex};
+use serde_json::value::RawValue;
+use toml_edit::{DocumentMut, Item};
+
+use crate::cargo::{self, Workspace};
+
+/// Tables of `pyproject.toml` that may define the version, by order of preference
+const PYPROJECT_TABLES: [&[&str]; 2] = [&["project"], &["tool", "poetry"]];
+
+/// File in which the version is written when releasing
+#[derive(Debug, Clone)]
+pub enum VersionFile {
+    /// Cargo manifest. For a workspace, the manifests of the members are updated as well.
+    Cargo(PathBuf),
+    PackageJson(PathBuf),
+    Pyproject(PathBuf),
+
+    /// Any file, in which the version is the first capture group (or the `version` named group) of the regex
+    Pattern(PathBuf, Regex),
+}
+
+/// New content of a file
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FileUpdate {
+    pub path: PathBuf,
+    pub before: String,
+    pub after: String,
+}
+
+impl VersionFile {
+    /// Infers the format from the file name, unless a pattern is given
+    pub fn new(path: PathBuf, pattern: Option<&str>) -> Result<Self, Error> {
+        if let Some(pattern) = pattern {
+            let regex =
+                Regex::new(pattern).map_err(|err| Error::InvalidPattern(path.clone(), err))?;
+            return Ok(Self::Pattern(path, regex));
+        }
+
+        match path.file_name().and_then(|it| it.to_str()) {
+            Some("Cargo.toml") => Ok(Self::Cargo(path)),
+            Some("package.json") => Ok(Self::PackageJson(path)),
+            Some("pyproject.toml") => Ok(Self::Pyproject(path)),
+            _ => Err(Error::UnknownFormat(path)),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            VersionFile::Cargo(path)
+            | VersionFile::PackageJson(path)
+            | VersionFile::Pyproject(path)
+            | VersionFile::Pattern(path, _) => path,
+        }
+    }
+
+    /// Reads the version currently written in the file
+    pub fn current_version(&self) -> Result<String, Error> {
+        let content = read(self.path())?;
+        self.read_version(&content)
+            .ok_or_else(|| Error::VersionNotFound(self.path().to_owned()))
+    }
+
+    /// Reads the version in `content`, which is the content of the file (possibly at another revision)
+    pub fn read_version(&self, content: &str) -> Option<String> {
+        match self {
+            VersionFile::Cargo(_) => cargo::manifest_version(content),
+            VersionFile::PackageJson(_) => find_json_version(content).map(|(_, version)| version),
+            VersionFile::Pyproject(_) => {
+                let document: DocumentMut = content.parse().ok()?;
+                find_toml_version(&document, &PYPROJECT_TABLES)?
+                    .as_str()
+                    .map(String::from)
+            }
+            VersionFile::Pattern(_, regex) => regex.captures(content).and_then(|captures| {
+                captures
+                    .name("version")
+                    .or_else(|| captures.get(1))
+                    .or_else(|| captures.get(0))
+                    .map(|it| String::from(it.as_str()))
+            }),
+        }
+    }
+
+    /// Computes the updates of the file(s), without writing anything
+    pub fn updates(&self, version: &str) -> Result<Vec<FileUpdate>, Error> {
+        match self {
+            VersionFile::Cargo(path) => {
+                let updates = Workspace::load(path)?.uniform_updates(version);
+                if updates.is_empty() {
+                    Err(Error::VersionNotFound(path.clone()))
+                } else {
+                    Ok(updates)
+                }
+            }
+            VersionFile::PackageJson(path) => update_file(path, |content| {
+                set_json_version(content, version)
+                    .ok_or_else(|| Error::VersionNotFound(path.clone()))
+            }),
+            VersionFile::Pyproject(path) => update_file(path, |content| {
+                let mut document: DocumentMut = content
+                    .parse()
+                    .map_err(|err| Error::InvalidToml(path.clone(), err))?;
+                set_toml_version(&mut document, &PYPROJECT_TABLES, version)
+                    .ok_or_else(|| Error::VersionNotFound(path.clone()))
+            }),
+            VersionFile::Pattern(path, regex) => update_file(path, |content| {
+                set_pattern_version(content, regex, version)
+                    .ok_or_else(|| Error::VersionNotFound(path.clone()))
+            }),
+        }
+        .map_err(|cause| match cause {
+            Error::VersionNotFound(_) => Error::VersionNotFound(self.path().to_owned()),
+            cause => cause,
+        })
+    }
+}
+
+/// Writes the version in all the files, and returns the paths of the updated files
+///
+/// In dry-run, the changes are printed but not written.
+pub fn update_all(
+    files: &[VersionFile],
+    version: &str,
+    dry_run: bool,
+) -> Result<Vec<PathBuf>, Error> {
+    let mut updated = Vec::new();
+    for file in files {
+        updated.extend(apply(file.updates(version)?, dry_run)?);
+    }
+    Ok(updated)
+}
+
+/// Writes the updates (unless in dry-run), and returns the paths of the updated files
+pub fn apply(updates: Vec<FileUpdate>, dry_run: bool) -> Result<Vec<PathBuf>, Error> {
+    let mut updated = Vec::with_capacity(updates.len());
+
+    for update in updates {
+        println!("> update {}", update.path.display());
+        print_diff(&update);
+
+        if !dry_run {
+            fs::write(&update.path, &update.after)
+                .map_err(|err| Error::CannotWriteFile(update.path.clone(), err))?;
+        }
+        updated.push(update.path);
+    }
+
+    Ok(updated)
+}
+
+fn print_diff(update: &FileUpdate) {
+    let lines = update.before.lines().zip(update.after.lines()).enumerate();
+    for (index, (before, after)) in lines {
+        if before != after {
+            println!("  {:>4} - {}", index + 1, before);
+            println!("  {:>4} + {}", index + 1, after);
+        }
+    }
+}
+
+fn update_file(
+    path: &Path,
+    update: impl FnOnce(&str) -> Result<String, Error>,
+) -> Result<Vec<FileUpdate>, Error> {
+    let before = read(path)?;
+    let after = update(&before)?;
+    Ok(vec![FileUpdate {
+        path: path.to_owned(),
+        before,
+        after,
+    }])
+}
+
+pub fn read(path: &Path) -> Result<String, Error> {
+    fs::read_to_string(path).map_err(|err| Error::CannotReadFile(path.to_owned(), err))
+}
+
+/// Replaces the value of a toml item if it is a string, keeping its formatting (spacing, comments)
+pub fn set_toml_string(item: &mut Item, value: &str) {
+    if let Some(current) = item.as_value_mut().filter(|it| it.is_str()) {
+        let decor = current.decor().clone();
+        *current = value.into();
+        *current.decor_mut() = decor;
+    }
+}
+
+/// Item of a toml document at the given path of keys (e.g. `["package", "version"]`)
+pub fn toml_item<'a>(document: &'a DocumentMut, keys: &[impl AsRef<str>]) -> Option<&'a Item> {
+    keys.iter().try_fold(document.as_item(), |item, key| {
+        item.as_table_like()?.get(key.as_ref())
+    })
+}
+
+/// Unlike `Item::get_mut`, doesn't insert the missing tables
+pub fn toml_item_mut<'a>(
+    document: &'a mut DocumentMut,
+    keys: &[impl AsRef<str>],
+) -> Option<&'a mut Item> {
+    keys.iter().try_fold(document.as_item_mut(), |item, key| {
+        item.as_table_like_mut()?.get_mut(key.as_ref())
+    })
+}
+
+fn set_pattern_version(content: &str, regex: &Regex, version: &str) -> Option<String> {
+    if !regex.is_match(content) {
+        return None;
+    }
+
+    Some(
+        regex
+            .replace_all(content, |captures: &Captures<'_>| {
+                let whole = captures.get(0).unwrap();
+                match captures.name("version").or_else(|| captures.get(1)) {
+                    None => version.to_owned(),
+                    Some(group) => format!(
+                        "{}{}{}",
+                        &content[whole.start()..group.start()],
+                        version,
+                        &content[group.end()..whole.end()]
+                    ),
+                }
+            })
+            .into_owned(),
+    )
+}
+
+/// Sets the top-level `version` of a json document, leaving the rest of the document untouched
+fn set_json_version(content: &str, version: &str) -> Option<String> {
+    let (span, _) = find_json_version(content)?;
+    let version = serde_json::to_string(version).ok()?;
+
+    Some(format!(
+        "{}{}{}",
+        &content[..span.start],
+        version,
+        &content[span.end..]
+    ))
+}
+
+/// Returns the span (including the quotes) and the value of the top-level `version` of a json document
+fn find_json_version(content: &str) -> Option<(Range<usize>, String)> {
+    let document: HashMap<String, &RawValue> = serde_json::from_str(content).ok()?;
+    let raw = document.get("version")?.get();
+    let version = serde_json::from_str(raw).ok()?;
+
+    // The raw value borrows the content
+    let start = raw.as_ptr() as usize - content.as_ptr() as usize;
+    Some((start..start + raw.len(), version))
+}
+
+/// Returns the `version` string of the first of the `tables` that defines one
+fn find_toml_version<'a>(document: &'a DocumentMut, tables: &[&[&str]]) -> Option<&'a Item> {
+    tables
+        .iter()
+        .filter_map(|table| toml_item(document, &[*table, &["version"]].concat()))
+        .find(|item| item.is_str())
+}
+
+/// Sets the `version` of the first of the `tables` that defines one
+fn set_toml_version(
+    document: &mut DocumentMut,
+    tables: &[&[&str]],
+    version: &str,
+) -> Option<String> {
+    let table = tables.iter().find(|table| {
+        matches!(toml_item(document, &[**table, &["version"]].concat()), Some(item) if item.is_str())
+    })?;
+    let item = toml_item_mut(document, &[*table, &["version"]].concat())?;
+    set_toml_string(item, version);
+    Some(document.to_string())
+}
+
+#[derive(Debug)]
+pub enum Error {
+    UnknownFormat(PathBuf),
+    InvalidPattern(PathBuf, regex::Error),
+    CannotReadFile(PathBuf, io::Error),
+    CannotWriteFile(PathBuf, io::Error),
+    InvalidToml(PathBuf, toml_edit::TomlError),
+    InvalidWorkspaceMember(PathBuf, String, glob::PatternError),
+    VersionNotFound(PathBuf),
+    ConflictsWithCargo(PathBuf),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnknownFormat(path) => write!(
+                f,
+                "Unknown format of version file {} (a pattern is required)",
+                path.display()
+            ),
+            Error::InvalidPattern(path, err) => write!(
+                f,
+                "Invalid pattern for version file {}: {}",
+                path.display(),
+                err
+            ),
+            Error::CannotReadFile(path, err) => {
+                write!(f, "Cannot read {}: {}", path.display(), err)
+            }
+            Error::CannotWriteFile(path, err) => {
+                write!(f, "Cannot write {}: {}", path.display(), err)
+            }
+            Error::InvalidToml(path, err) => {
+                write!(f, "Invalid toml in {}: {}", path.display(), err)
+            }
+            Error::InvalidWorkspaceMember(path, member, err) => write!(
+                f,
+                "Invalid workspace member '{}' in {}: {}",
+                member,
+                path.display(),
+                err
+            ),
+            Error::VersionNotFound(path) => write!(f, "No version found in {}", path.display()),
+            Error::ConflictsWithCargo(path) => write!(
+                f,
+                "The version file {} would give all the cargo members the same version, \
+                 which conflicts with the per-member versions of the `cargo` section (remove one of them)",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case("Cargo.toml", true)]
+    #[case("changelog/Cargo.toml", true)]
+    #[case("package.json", true)]
+    #[case("pyproject.toml", true)]
+    #[case("VERSION", false)]
+    fn infer_format_from_file_name(#[case] path: &str, #[case] expected_known: bool) {
+        assert_eq!(
+            VersionFile::new(PathBuf::from(path), None).is_ok(),
+            expected_known
+        )
+    }
+
+    #[rstest]
+    #[case(
+        "Cargo.toml",
+        "[package]\nname = \"a\"\nversion = \"1.2.3\"\n",
+        Some("1.2.3")
+    )]
+    #[case(
+        "Cargo.toml",
+        "[workspace]\nmembers = [\"a\"]\n\n[workspace.package]\nversion = \"1.2.3\"\n",
+        Some("1.2.3")
+    )]
+    #[case("Cargo.toml", "[workspace]\nmembers = [\"a\"]\n", None)]
+    #[case(
+        "package.json",
+        "{ \"dependencies\": { \"version\": \"0.0.1\" }, \"version\": \"1.2.3\" }",
+        Some("1.2.3")
+    )]
+    #[case(
+        "package.json",
+        "{ \"description\": \"{ \\\"version\\\": \\\"0.0.1\\\" }\", \"version\": \"1.2.3\" }",
+        Some("1.2.3")
+    )]
+    #[case("package.json", "{ \"versions\": [\"1.2.3\"] }", None)]
+    #[case(
+        "pyproject.toml",
+        "[tool.poetry]\nversion = \"1.2.3\"\n",
+        Some("1.2.3")
+    )]
+    #[case(
+        "pyproject.toml",
+        "[tool]\npoetry.version = \"1.2.3\"\n",
+        Some("1.2.3")
+    )]
+    fn read_version(#[case] path: &str, #[case] content: &str, #[case] expected: Option<&str>) {
+        let file = VersionFile::new(PathBuf::from(path), None).expect("Unknown format");
+
+        assert_eq!(file.read_version(content).as_deref(), expected)
+    }
+
+    #[rstest]
+    #[case(r#"VERSION = "(.*)""#, Some("1.2.3"))]
+    #[case(r#"VERSION = "(?P<version>.*)""#, Some("1.2.3"))]
+    #[case(r#"\d+\.\d+\.\d+"#, Some("1.2.3"))]
+    #[case(r#"NAME = "(.*)""#, None)]
+    fn read_version_with_pattern(#[case] pattern: &str, #[case] expected: Option<&str>) {
+        let file =
+            VersionFile::new(PathBuf::from("VERSION"), Some(pattern)).expect("Invalid pattern");
+
+        assert_eq!(
+            file.read_version("VERSION = \"1.2.3\"\n").as_deref(),
+            expected
+        )
+    }
+
+    #[rstest]
+    #[case(
+        "{\n  \"name\": \"app\",\n  \"dependencies\": { \"x\": { \"version\": \"1.0.0\" } },\n  \"version\": \"0.1.0\"\n}\n",
+        Some("{\n  \"name\": \"app\",\n  \"dependencies\": { \"x\": { \"version\": \"1.0.0\" } },\n  \"version\": \"2.0.0\"\n}\n")
+    )]
+    #[case(
+        "{\n  \"scripts\": { \"build\": \"echo }\" },\n  \"version\": \"0.1.0\" // comment\n}\n",
+        None
+    )]
+    #[case(
+        "{\n  \"scripts\": { \"build\": \"echo } \\\"version\\\": \\\"0.0.1\\\"\" },\n  \"version\": \"0.1.0\"\n}\n",
+        Some("{\n  \"scripts\": { \"build\": \"echo } \\\"version\\\": \\\"0.0.1\\\"\" },\n  \"version\": \"2.0.0\"\n}\n")
+    )]
+    #[case("{ \"name\": \"app\" }", None)]
+    fn package_json_file(#[case] content: &str, #[case] expected: Option<&str>) {
+        assert_eq!(set_json_version(content, "2.0.0").as_deref(), expected)
+    }
+
+    #[rstest]
+    #[case(
+        "[project]\nname = \"app\"\nversion = \"0.1.0\"\n",
+        Some("[project]\nname = \"app\"\nversion = \"2.0.0\"\n")
+    )]
+    #[case(
+        "[build-system]\nrequires = []\n\n[tool.poetry]\ndescription = \"\"\"\nversion = '0.1.0'\n\"\"\"\nversion = \"0.1.0\" # bumped\n",
+        Some("[build-system]\nrequires = []\n\n[tool.poetry]\ndescription = \"\"\"\nversion = '0.1.0'\n\"\"\"\nversion = \"2.0.0\" # bumped\n")
+    )]
+    #[case(
+        "[tool]\npoetry.version = \"0.1.0\"\n",
+        Some("[tool]\npoetry.version = \"2.0.0\"\n")
+    )]
+    #[case(
+        "[project]\ndynamic = [\"version\"]\n\n[tool.poetry]\nversion = \"0.1.0\"\n",
+        Some("[project]\ndynamic = [\"version\"]\n\n[tool.poetry]\nversion = \"2.0.0\"\n")
+    )]
+    #[case("[tool.black]\nversion = \"0.1.0\"\n", None)]
+    fn pyproject_file(#[case] content: &str, #[case] expected: Option<&str>) {
+        let mut document: DocumentMut = content.parse().expect("Invalid toml");
+
+        assert_eq!(
+            set_toml_version(&mut document, &PYPROJECT_TABLES, "2.0.0").as_deref(),
+            expected
+        )
+    }
+
+    #[rstest]
+    #[case(
+        r#"VERSION = "(.*)""#,
+        "VERSION = \"0.1.0\"\n",
+        Some("VERSION = \"2.0.0\"\n")
+    )]
+    #[case(
+        r#"v(?P<version>\d+\.\d+\.\d+) \((\w+)\)"#,
+        "v0.1.0 (beta)",
+        Some("v2.0.0 (beta)")
+    )]
+    #[case(r#"\d+\.\d+\.\d+"#, "Release 0.1.0", Some("Release 2.0.0"))]
+    #[case(r#"VERSION = "(.*)""#, "NAME = \"x\"", None)]
+    fn pattern_file(#[case] pattern: &str, #[case] content: &str, #[case] expected: Option<&str>) {
+        let regex = Regex::new(pattern).expect("Invalid regex");
+
+        assert_eq!(
+            set_pattern_version(content, &regex, "2.0.0").as_deref(),
+            expected
+        )
+    }
+}